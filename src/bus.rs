@@ -0,0 +1,306 @@
+use crate::cartridge::Cartridge;
+
+//DEFINES
+
+//NES specific hardware defines
+
+pub static PPU_CTRL_REG1      : u16 = 0x2000;
+pub static PPU_CTRL_REG2      : u16 = 0x2001;
+pub static PPU_STATUS         : u16 = 0x2002;
+pub static PPU_SPR_ADDR       : u16 = 0x2003;
+pub static PPU_SPR_DATA       : u16 = 0x2004;
+pub static PPU_SCROLL_REG     : u16 = 0x2005;
+pub static PPU_ADDRESS        : u16 = 0x2006;
+pub static PPU_DATA           : u16 = 0x2007;
+
+pub static SND_REGISTER       : u16 = 0x4000;
+pub static SND_SQUARE1_REG    : u16 = 0x4000;
+pub static SND_SQUARE2_REG    : u16 = 0x4004;
+pub static SND_TRIANGLE_REG   : u16 = 0x4008;
+pub static SND_NOISE_REG      : u16 = 0x400C;
+pub static SND_DELTA_REG      : u16 = 0x4010;
+pub static SND_MASTERCTRL_REG : u16 = 0x4015;
+
+pub static SPR_DMA            : u16 = 0x4014;
+pub static JOYPAD_PORT        : u16 = 0x4016;
+pub static JOYPAD_PORT1       : u16 = 0x4016;
+pub static JOYPAD_PORT2       : u16 = 0x4017;
+
+// Every CPU memory access goes through a Bus, so whatever sits
+// behind an address gets to see (and react to) the access.
+pub trait Bus
+{
+    fn read(&mut self, address: u16) -> u8;
+    fn write(&mut self, address: u16, val: u8);
+
+    // read without side effects, for debuggers and tracing
+    // buses whose reads never have side effects can rely on read
+    fn peek(&mut self, address: u16) -> u8
+    {
+        return self.read(address);
+    }
+
+    // cycles the CPU has to sit out because of the last access, e.g. OAM DMA
+    fn take_stall_cycles(&mut self) -> u16
+    {
+        return 0;
+    }
+
+    // CPU::step reports the cycles each instruction or interrupt took, so
+    // devices with their own timers can catch up
+    fn tick(&mut self, _cycles: u16)
+    {
+    }
+
+    // level of the IRQ line as driven by devices on the bus, e.g. a mapper's scanline counter
+    fn irq_line(&self) -> bool
+    {
+        return false;
+    }
+}
+
+// CPU memory map of the NES
+// 0x0000 - 0x07FF 2KB internal RAM, mirrored through 0x1FFF
+// 0x2000 - 0x2007 PPU registers, mirrored through 0x3FFF
+// 0x4000 - 0x401F APU and I/O registers
+// 0x4020 - 0xFFFF cartridge space, decoded by the board's mapper
+#[derive(Debug)]
+pub struct NesBus
+{
+    ram: Vec<u8>,
+    ppu_registers: [u8; 8],
+    apu_io_registers: [u8; 0x20],
+    oam: Vec<u8>, // sprite memory, filled through SPR_DMA
+    stall_cycles: u16,
+    cartridge: Option<Cartridge>,
+}
+
+pub fn init_nes_bus() -> NesBus
+{
+    let mut bus = NesBus
+    {
+        ram: vec![0x0; 0x0800],
+        ppu_registers: [0x0; 8],
+        apu_io_registers: [0x0; 0x20],
+        oam: vec![0x0; 0x100],
+        stall_cycles: 0,
+        cartridge: None,
+    };
+    // no PPU yet, so report vblank as always in progress
+    bus.ppu_registers[(PPU_STATUS & 0x0007) as usize] = 0b10100000;
+    return bus;
+}
+
+impl NesBus
+{
+    pub fn insert_cartridge(&mut self, cartridge: Cartridge)
+    {
+        self.cartridge = Some(cartridge);
+    }
+
+    pub fn get_cartridge(&self) -> Option<&Cartridge>
+    {
+        return self.cartridge.as_ref();
+    }
+
+    pub fn get_cartridge_mut(&mut self) -> Option<&mut Cartridge>
+    {
+        return self.cartridge.as_mut();
+    }
+
+    // the console's audio output on the APU's 0.0 - 1.0 scale. There is no
+    // APU yet, so this is only the cartridge's expansion sound for now.
+    pub fn audio_sample(&self) -> f32
+    {
+        return match self.cartridge
+        {
+            Some(ref cartridge) => cartridge.audio_output(),
+            None => 0.0,
+        };
+    }
+
+    pub fn get_ppu_register(&self, address: u16) -> u8
+    {
+        return self.ppu_registers[(address & 0x0007) as usize];
+    }
+
+    pub fn get_apu_io_register(&self, address: u16) -> u8
+    {
+        return self.apu_io_registers[(address & 0x001F) as usize];
+    }
+
+    pub fn get_oam(&self) -> &Vec<u8>
+    {
+        return &self.oam;
+    }
+
+    // copies page 0xXX00 - 0xXXFF into OAM starting at the current sprite address
+    fn oam_dma(&mut self, page: u8)
+    {
+        let mut oam_address = self.get_ppu_register(PPU_SPR_ADDR);
+        for i in 0..0x100
+        {
+            let val = self.read(((page as u16) << 8) | i);
+            self.oam[oam_address as usize] = val;
+            oam_address = oam_address.wrapping_add(1);
+        }
+        self.stall_cycles = 513;
+    }
+}
+
+impl Bus for NesBus
+{
+    fn read(&mut self, address: u16) -> u8
+    {
+        match address
+        {
+            0x0000..=0x1FFF => self.ram[(address & 0x07FF) as usize],
+            0x2000..=0x3FFF => self.ppu_registers[(address & 0x0007) as usize],
+            0x4000..=0x401F => self.apu_io_registers[(address - 0x4000) as usize],
+            _               => match self.cartridge
+            {
+                Some(ref mut cartridge) => cartridge.cpu_read(address),
+                None => 0x0, // open bus with an empty slot
+            },
+        }
+    }
+
+    fn peek(&mut self, address: u16) -> u8
+    {
+        return match (address, self.cartridge.as_mut())
+        {
+            // the APU and I/O registers are write-only or clear state when
+            // read, so debuggers see FF there like they do in nestest.log
+            (0x4000..=0x4017, _) => 0xFF,
+            (0x4020..=0xFFFF, Some(cartridge)) => cartridge.cpu_peek(address),
+            _ => self.read(address),
+        };
+    }
+
+    fn write(&mut self, address: u16, val: u8)
+    {
+        match address
+        {
+            0x0000..=0x1FFF => self.ram[(address & 0x07FF) as usize] = val,
+            0x2000..=0x3FFF =>
+            {
+                self.ppu_registers[(address & 0x0007) as usize] = val;
+                // boards like the MMC5 snoop the PPU registers
+                if let Some(ref mut cartridge) = self.cartridge
+                {
+                    cartridge.cpu_write(address, val);
+                }
+            },
+            0x4014          => self.oam_dma(val), // SPR_DMA
+            0x4000..=0x401F => self.apu_io_registers[(address - 0x4000) as usize] = val,
+            _               =>
+            {
+                if let Some(ref mut cartridge) = self.cartridge
+                {
+                    cartridge.cpu_write(address, val);
+                }
+            },
+        }
+    }
+
+    fn take_stall_cycles(&mut self) -> u16
+    {
+        let stall = self.stall_cycles;
+        self.stall_cycles = 0;
+        return stall;
+    }
+
+    fn tick(&mut self, cycles: u16)
+    {
+        if let Some(ref mut cartridge) = self.cartridge
+        {
+            for _ in 0..cycles
+            {
+                cartridge.cpu_clock();
+            }
+        }
+    }
+
+    fn irq_line(&self) -> bool
+    {
+        return match self.cartridge
+        {
+            Some(ref cartridge) => cartridge.irq_pending(),
+            None => false,
+        };
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BusAccess
+{
+    Read(u16, u8),
+    Write(u16, u8),
+}
+
+// 64KB of plain RAM with no mirroring or registers, for running generic
+// 6502 code and tests. Once logging is turned on every read and write is
+// recorded in order, until the log is cleared.
+#[derive(Debug, Clone)]
+pub struct FlatBus
+{
+    memory: Vec<u8>,
+    log_accesses: bool,
+    accesses: Vec<BusAccess>,
+}
+
+pub fn init_flat_bus() -> FlatBus
+{
+    let bus = FlatBus
+    {
+        memory: vec![0x0; 0x10000],
+        log_accesses: false,
+        accesses: Vec::new(),
+    };
+    return bus;
+}
+
+impl FlatBus
+{
+    pub fn set_log_accesses(&mut self, log_accesses: bool)
+    {
+        self.log_accesses = log_accesses;
+    }
+
+    pub fn get_accesses(&self) -> &Vec<BusAccess>
+    {
+        return &self.accesses;
+    }
+
+    pub fn clear_accesses(&mut self)
+    {
+        self.accesses.clear();
+    }
+}
+
+impl Bus for FlatBus
+{
+    fn read(&mut self, address: u16) -> u8
+    {
+        let val = self.memory[address as usize];
+        if self.log_accesses
+        {
+            self.accesses.push(BusAccess::Read(address, val));
+        }
+        return val;
+    }
+
+    fn write(&mut self, address: u16, val: u8)
+    {
+        self.memory[address as usize] = val;
+        if self.log_accesses
+        {
+            self.accesses.push(BusAccess::Write(address, val));
+        }
+    }
+
+    fn peek(&mut self, address: u16) -> u8
+    {
+        return self.memory[address as usize];
+    }
+}
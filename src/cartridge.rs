@@ -0,0 +1,193 @@
+use crate::file_handling::*;
+use crate::header::*;
+use crate::mapper::*;
+
+// trainers are loaded into PRG-RAM at 0x7000
+static TRAINER_OFFSET: usize = 0x1000;
+
+// Everything on the game board: the memories split out of the iNES file
+// and the mapper that decides how the CPU and PPU see them.
+#[derive(Debug)]
+pub struct Cartridge
+{
+    header: Header,
+    prg_rom: Vec<u8>,
+    chr: Vec<u8>, // CHR-ROM, or CHR-RAM when the file has none
+    chr_is_ram: bool,
+    prg_ram: Vec<u8>, // volatile and battery backed PRG-RAM together
+    trainer: Option<Vec<u8>>,
+    mapper: Box<dyn Mapper>,
+}
+
+pub fn init_cartridge(rom: Rom) -> Result<Cartridge, RomError>
+{
+    let h = rom.get_header().clone();
+    let mapper = match create_mapper(&h)
+    {
+        Some(mapper) => mapper,
+        None => return Err(RomError::UnsupportedMapper(h.get_mapper())),
+    };
+
+    let chr_is_ram = rom.get_chr_rom().is_empty();
+    let chr = if chr_is_ram
+    {
+        vec![0x0; h.get_chr_ram_size() + h.get_chr_nvram_size()]
+    }
+    else
+    {
+        rom.get_chr_rom().clone()
+    };
+
+    let mut prg_ram = vec![0x0; h.get_prg_ram_size() + h.get_prg_nvram_size()];
+    if let Some(trainer) = rom.get_trainer()
+    {
+        if prg_ram.len() < TRAINER_OFFSET + trainer.len()
+        {
+            prg_ram.resize(TRAINER_OFFSET + trainer.len(), 0x0);
+        }
+        prg_ram[TRAINER_OFFSET..TRAINER_OFFSET + trainer.len()].copy_from_slice(trainer);
+    }
+
+    let cartridge = Cartridge
+    {
+        header: h,
+        prg_rom: rom.get_prg_rom().clone(),
+        chr,
+        chr_is_ram,
+        prg_ram,
+        trainer: rom.get_trainer().cloned(),
+        mapper,
+    };
+    return Ok(cartridge);
+}
+
+// memories smaller than the window they are mapped into repeat
+fn read_wrapped(memory: &[u8], offset: usize) -> u8
+{
+    if memory.is_empty()
+    {
+        return 0x0;
+    }
+    return memory[offset % memory.len()];
+}
+
+fn write_wrapped(memory: &mut [u8], offset: usize, val: u8)
+{
+    if !memory.is_empty()
+    {
+        let len = memory.len();
+        memory[offset % len] = val;
+    }
+}
+
+impl Cartridge
+{
+    pub fn get_header(&self) -> &Header
+    {
+        return &self.header;
+    }
+
+    pub fn get_prg_rom(&self) -> &Vec<u8>
+    {
+        return &self.prg_rom;
+    }
+
+    pub fn get_chr(&self) -> &Vec<u8>
+    {
+        return &self.chr;
+    }
+
+    pub fn has_chr_ram(&self) -> bool
+    {
+        return self.chr_is_ram;
+    }
+
+    pub fn get_prg_ram(&self) -> &Vec<u8>
+    {
+        return &self.prg_ram;
+    }
+
+    pub fn get_trainer(&self) -> Option<&Vec<u8>>
+    {
+        return self.trainer.as_ref();
+    }
+
+    pub fn get_mirroring(&self) -> Mirroring
+    {
+        return self.mapper.get_mirroring();
+    }
+
+    pub fn irq_pending(&self) -> bool
+    {
+        return self.mapper.irq_pending();
+    }
+
+    pub fn cpu_clock(&mut self)
+    {
+        self.mapper.cpu_clock();
+    }
+
+    pub fn audio_output(&self) -> f32
+    {
+        return self.mapper.audio_output();
+    }
+
+    fn read_mapped(&self, mapped: Mapped) -> u8
+    {
+        return match mapped
+        {
+            Mapped::PrgRom(offset) => read_wrapped(&self.prg_rom, offset),
+            Mapped::PrgRam(offset) => read_wrapped(&self.prg_ram, offset),
+            Mapped::Chr(offset)    => read_wrapped(&self.chr, offset),
+            Mapped::Value(val)     => val,
+            Mapped::Ciram(_)       => 0x0, // console memory, the PPU answers it itself
+            Mapped::Nothing        => 0x0,
+        };
+    }
+
+    fn write_mapped(&mut self, mapped: Mapped, val: u8)
+    {
+        match mapped
+        {
+            Mapped::PrgRam(offset) => write_wrapped(&mut self.prg_ram, offset, val),
+            Mapped::Chr(offset) if self.chr_is_ram => write_wrapped(&mut self.chr, offset, val),
+            _ => (), // ROM cannot be written
+        }
+    }
+
+    pub fn cpu_read(&mut self, address: u16) -> u8
+    {
+        let mapped = self.mapper.cpu_read(address);
+        return self.read_mapped(mapped);
+    }
+
+    pub fn cpu_peek(&mut self, address: u16) -> u8
+    {
+        let mapped = self.mapper.cpu_peek(address);
+        return self.read_mapped(mapped);
+    }
+
+    pub fn cpu_write(&mut self, address: u16, val: u8)
+    {
+        let mut val = val;
+        if address >= 0x8000 && self.mapper.has_bus_conflicts()
+        {
+            let rom = self.mapper.cpu_peek(address);
+            val &= self.read_mapped(rom);
+        }
+        let mapped = self.mapper.cpu_write(address, val);
+        self.write_mapped(mapped, val);
+    }
+
+    pub fn ppu_read(&mut self, address: u16) -> u8
+    {
+        let mapped = self.mapper.ppu_read(address);
+        return self.read_mapped(mapped);
+    }
+
+    pub fn ppu_write(&mut self, address: u16, val: u8)
+    {
+        let mapped = self.mapper.ppu_write(address, val);
+        self.write_mapped(mapped, val);
+    }
+}
@@ -1,35 +1,29 @@
-use opcode::*;
+use crate::opcode::*;
+use crate::bus::Bus;
 
-//DEFINES
+// interrupt vectors, each holds the little endian address of its handler
+static NMI_VECTOR   : u16 = 0xFFFA;
+static RESET_VECTOR : u16 = 0xFFFC;
+static IRQ_VECTOR   : u16 = 0xFFFE; // shared by BRK
 
-//NES specific hardware defines
-
-static PPU_CTRL_REG1      : u16 = 0x2000;
-static PPU_CTRL_REG2      : u16 = 0x2001;
-static PPU_STATUS         : u16 = 0x2002;
-static PPU_SPR_ADDR       : u16 = 0x2003;
-static PPU_SPR_DATA       : u16 = 0x2004;
-static PPU_SCROLL_REG     : u16 = 0x2005;
-static PPU_ADDRESS        : u16 = 0x2006;
-static PPU_DATA           : u16 = 0x2007;
-
-static SND_REGISTER       : u16 = 0x4000;
-static SND_SQUARE1_REG    : u16 = 0x4000;
-static SND_SQUARE2_REG    : u16 = 0x4004;
-static SND_TRIANGLE_REG   : u16 = 0x4008;
-static SND_NOISE_REG      : u16 = 0x400C;
-static SND_DELTA_REG      : u16 = 0x4010;
-static SND_MASTERCTRL_REG : u16 = 0x4015;
-
-static SPR_DMA            : u16 = 0x4014;
-static JOYPAD_PORT        : u16 = 0x4016;
-static JOYPAD_PORT1       : u16 = 0x4016;
-static JOYPAD_PORT2       : u16 = 0x4017;
+static STACK_PAGE   : u16 = 0x0100;
 
+// XAA and LXA mix A with whatever the bus happens to hold,
+// 0xEE is the value most commonly observed on real chips
+static UNSTABLE_MAGIC : u8 = 0xEE;
 
+// Which chip the core behaves like.
+// The NES 2A03 has the decimal flag but no BCD circuitry,
+// a stock NMOS 6502 honours the flag in ADC and SBC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant
+{
+    Ricoh2A03,
+    Nmos6502,
+}
 
 #[derive(Debug, Clone)]
-pub struct CPU
+pub struct CPU<B: Bus>
 {
     // program counter register
     // accessed internally by cpu fetch logic to increment,
@@ -39,38 +33,57 @@ pub struct CPU
     a: u8, // accumulator
     x: u8, // x-index, used for address modes
     y: u8, // y-index, used for address modes
-    s: u16, // stack pointer, accessed using interrupts, pulls, pushes, and transfers
-    // points between 0x0100 and 0x01FF in memory
-    stack_offset: u16,
+    s: u8, // stack pointer, accessed using interrupts, pulls, pushes, and transfers
+    // offset into STACK_PAGE, so the stack lives between 0x0100 and 0x01FF in memory
     p: u8, // flag register
     // NVssDIZC
     instruction: u8, // current instruction cpu is processing
     first_byte_of_interest: u8, // first byte following opcode
     second_byte_of_interest: u8, // second byte following opcode, may be of interest
-    memory: Vec<u16>,
+    halted: bool, // set by KIL, only a reset gets the cpu going again
+    variant: Variant,
+    cycles: u64, // total cycles run since power up
+    extra_cycles: u16, // branch penalties picked up by the current instruction
+    page_crossed: bool, // set when indexing the current operand crossed a page
+    nmi_line: bool, // level of the NMI input, only a rising edge raises an NMI
+    nmi_pending: bool, // an NMI edge was seen and has not been serviced yet
+    irq_line: bool, // level of the IRQ input, held by the device until acknowledged
+    irq_disabled_at_poll: bool, // the I flag as the last instruction's interrupt poll saw it
+    poll_skipped: bool, // the last instruction or interrupt sequence skipped its final poll
+    early_poll_due: bool, // what an instruction that skipped its final poll saw at its early one
+    bus: B, // everything the cpu can address
 }
 
-pub fn init_cpu() -> CPU
+pub fn init_cpu<B: Bus>(bus: B) -> CPU<B>
 {
-    let mut cpu = CPU
+    let cpu = CPU
     {
-        pc: 0x10,
+        pc: 0x0, // loaded from the reset vector by reset()
         a: 0x0,
         x: 0x0,
         y: 0x0,
-        s: 0x1000,
-        stack_offset: 0x1000,
-        p: 0b11010000,
+        s: 0x0,
+        p: 0b00100000, // bit 5 always reads back as set
         instruction: 0x0,
         first_byte_of_interest: 0x0,
         second_byte_of_interest: 0x0,
-        memory: vec![0x0; 0xFFFF],
+        halted: false,
+        variant: Variant::Ricoh2A03,
+        cycles: 0,
+        extra_cycles: 0,
+        page_crossed: false,
+        nmi_line: false,
+        nmi_pending: false,
+        irq_line: false,
+        irq_disabled_at_poll: true,
+        poll_skipped: false,
+        early_poll_due: false,
+        bus,
     };
-    cpu.memory[PPU_STATUS as usize] = 0b10100000;
     return cpu;
 }
 
-impl CPU
+impl<B: Bus> CPU<B>
 {
     pub fn set_instruction(&mut self, instruction: u8)
     {
@@ -79,7 +92,7 @@ impl CPU
 
     pub fn get_instruction(&self) -> u8
     {
-        return self.instruction.clone();
+        return self.instruction;
     }
 
     pub fn set_first_byte_of_interest(&mut self, first_byte_of_interest: u8)
@@ -107,9 +120,46 @@ impl CPU
         return self.pc;
     }
 
+    pub fn set_pc(&mut self, val: u16)
+    {
+        self.pc = val;
+    }
+
+    pub fn get_p(&self) -> u8
+    {
+        return self.p;
+    }
+
+    pub fn set_p(&mut self, val: u8)
+    {
+        self.p = val;
+        // a flag register set from outside is what the next poll sees
+        self.irq_disabled_at_poll = (val & 0b00000100) != 0;
+    }
+
+    pub fn get_variant(&self) -> Variant
+    {
+        return self.variant;
+    }
+
+    pub fn set_variant(&mut self, variant: Variant)
+    {
+        self.variant = variant;
+    }
+
+    pub fn is_halted(&self) -> bool
+    {
+        return self.halted;
+    }
+
+    pub fn get_cycles(&self) -> u64
+    {
+        return self.cycles;
+    }
+
     pub fn increment_pc(&mut self, val: u16)
     {
-        self.pc += val;
+        self.pc = self.pc.wrapping_add(val); // the pc wraps from 0xFFFF to 0x0000
     }
 
     pub fn get_a(&self) -> u8
@@ -142,14 +192,14 @@ impl CPU
         self.y = val;
     }
 
-    pub fn get_s(&self) -> u16
+    pub fn get_s(&self) -> u8
     {
         return self.s;
     }
 
-    pub fn set_s(&mut self, val: u16)
+    pub fn set_s(&mut self, val: u8)
     {
-        self.s = 0x1000 + val;
+        self.s = val;
     }
 
     pub fn get_carry_flag(&self) -> u8
@@ -242,712 +292,997 @@ impl CPU
         self.p &= 0b01111111;
     }
 
-    pub fn execute_opcode(&mut self, op: Opcode)
-    {
-        let code = get_opcode_code(op);
-        let ln = get_left_nibble(code);
-        let rn = get_right_nibble(code);
-
-        match ln
-        {
-            0x0 => self.left_nib_is_0(rn),
-            0x1 => self.left_nib_is_1(rn),
-            0x2 => self.left_nib_is_2(rn),
-            0x3 => self.left_nib_is_3(rn),
-            0x4 => self.left_nib_is_4(rn),
-            0x5 => self.left_nib_is_5(rn),
-            0x6 => self.left_nib_is_6(rn),
-            0x7 => self.left_nib_is_7(rn),
-            0x8 => self.left_nib_is_8(rn),
-            0x9 => self.left_nib_is_9(rn),
-            0xA => self.left_nib_is_a(rn),
-            0xB => self.left_nib_is_b(rn),
-            0xC => self.left_nib_is_c(rn),
-            0xD => self.left_nib_is_d(rn),
-            0xE => self.left_nib_is_e(rn),
-            0xF => self.left_nib_is_f(rn),
-            _   => println!("Unsure about this nib."),
-        }
-    }
-
-    fn left_nib_is_0(&mut self, rn: u8)
-    {
-        match rn
-        {
-            0x00 => self.brk(),
-            0x01 => self.ora_indirect_x(),
-            0x02 => return,
-            0x03 => return ,
-            0x04 => return,
-            0x05 => return ,
-            0x06 => return ,
-            0x07 => return ,
-            0x08 => return ,
-            0x09 => return,
-            0x0A => return ,
-            0x0B => return ,
-            0x0C => return ,
-            0x0D => return,
-            0x0E => return ,
-            0x0F => return ,
-            _    => return ,
-        }
-    }
-
-    fn left_nib_is_1(&mut self, rn: u8)
-    {
-        match rn
-        {
-            0x00 => self.bpl(),
-            0x01 => return ,
-            0x02 => return ,
-            0x03 => return ,
-            0x04 => return ,
-            0x05 => return ,
-            0x06 => return ,
-            0x07 => return ,
-            0x08 => return ,
-            0x09 => return ,
-            0x0A => return ,
-            0x0B => return ,
-            0x0C => return ,
-            0x0D => return,
-            0x0E => return ,
-            0x0F => return ,
-            _    => return ,
-        }
-    }
-
-    fn left_nib_is_2(&mut self, rn: u8)
-    {
-        match rn
-        {
-            0x00 => return,
-            0x01 => return ,
-            0x02 => return,
-            0x03 => return ,
-            0x04 => return,
-            0x05 => return ,
-            0x06 => return ,
-            0x07 => return ,
-            0x08 => return ,
-            0x09 => return,
-            0x0A => return ,
-            0x0B => return ,
-            0x0C => return ,
-            0x0D => return,
-            0x0E => return ,
-            0x0F => return ,
-            _    => return ,
-        }
-    }
-
-    fn left_nib_is_3(&mut self, rn: u8)
-    {
-        match rn
-        {
-            0x00 => return ,
-            0x01 => self.and_indirect_y(),
-            0x02 => return ,
-            0x03 => return ,
-            0x04 => return ,
-            0x05 => return ,
-            0x06 => return ,
-            0x07 => return ,
-            0x08 => return ,
-            0x09 => return ,
-            0x0A => return ,
-            0x0B => return ,
-            0x0C => return ,
-            0x0D => return ,
-            0x0E => return ,
-            0x0F => return ,
-            _    => return ,
-        }
-    }
-
-    fn left_nib_is_4(&mut self, rn: u8)
-    {
-        match rn
-        {
-            0x00 => return,
-            0x01 => return ,
-            0x02 => return,
-            0x03 => return ,
-            0x04 => return,
-            0x05 => return ,
-            0x06 => return ,
-            0x07 => return ,
-            0x08 => return ,
-            0x09 => return,
-            0x0A => return ,
-            0x0B => return ,
-            0x0C => return ,
-            0x0D => return,
-            0x0E => return ,
-            0x0F => return ,
-            _    => return ,
-        }
-    }
-
-    fn left_nib_is_5(&mut self, rn: u8)
-    {
-        match rn
-        {
-            0x00 => return,
-            0x01 => return ,
-            0x02 => return,
-            0x03 => return ,
-            0x04 => return,
-            0x05 => return ,
-            0x06 => return ,
-            0x07 => return ,
-            0x08 => return ,
-            0x09 => return,
-            0x0A => return ,
-            0x0B => return ,
-            0x0C => return ,
-            0x0D => return,
-            0x0E => return ,
-            0x0F => return ,
-            _    => return ,
-        }
-    }
-
-    fn left_nib_is_6(&mut self, rn: u8)
-    {
-        match rn
-        {
-            0x00 => return,
-            0x01 => return ,
-            0x02 => return,
-            0x03 => return ,
-            0x04 => return,
-            0x05 => return ,
-            0x06 => return ,
-            0x07 => return ,
-            0x08 => return ,
-            0x09 => return,
-            0x0A => return ,
-            0x0B => return ,
-            0x0C => return ,
-            0x0D => return,
-            0x0E => return ,
-            0x0F => return ,
-            _    => return ,
-        }
-    }
-
-    fn left_nib_is_7(&mut self, rn: u8)
-    {
-        match rn
-        {
-            0x00 => return ,
-            0x01 => return,
-            0x02 => return ,
-            0x03 => return ,
-            0x04 => return ,
-            0x05 => return ,
-            0x06 => return ,
-            0x07 => return ,
-            0x08 => self.sei(),
-            0x09 => return ,
-            0x0A => return ,
-            0x0B => return ,
-            0x0C => return ,
-            0x0D => return ,
-            0x0E => return ,
-            0x0F => return ,
-            _    => return ,
-        }
-    }
-
-    fn left_nib_is_8(&mut self, rn: u8)
-    {
-        match rn
-        {
-            0x00 => return ,
-            0x01 => return ,
-            0x02 => return ,
-            0x03 => return ,
-            0x04 => return ,
-            0x05 => return ,
-            0x06 => return ,
-            0x07 => return ,
-            0x08 => self.dey(),
-            0x09 => return ,
-            0x0A => self.txa(),
-            0x0B => return ,
-            0x0C => return ,
-            0x0D => self.sta_absolute(),
-            0x0E => return ,
-            0x0F => return ,
-            _    => return ,
-        }
-    }
-
-    fn left_nib_is_9(&mut self, rn: u8)
-    {
-        match rn
-        {
-            0x00 => return ,
-            0x01 => return ,
-            0x02 => return,
-            0x03 => return ,
-            0x04 => return ,
-            0x05 => return ,
-            0x06 => return ,
-            0x07 => return ,
-            0x08 => self.tya(),
-            0x09 => return,
-            0x0A => self.txs(),
-            0x0B => return ,
-            0x0C => return ,
-            0x0D => return ,
-            0x0E => return ,
-            0x0F => return ,
-            _    => return ,
-        }
-    }
-
-    fn left_nib_is_a(&mut self, rn: u8)
-    {
-        match rn
-        {
-            0x00 => self.ldy_immediate(),
-            0x01 => self.lda_indirect_x(),
-            0x02 => self.ldx_immediate(),
-            0x03 => return ,
-            0x04 => self.ldy_zero_page(),
-            0x05 => self.lda_zero_page(),
-            0x06 => self.ldx_zero_page(),
-            0x07 => return ,
-            0x08 => self.tay(),
-            0x09 => self.lda_immediate(),
-            0x0A => self.tax(),
-            0x0B => return ,
-            0x0C => self.ldy_absolute(),
-            0x0D => self.lda_absolute(),
-            0x0E => self.ldx_absolute(),
-            0x0F => return ,
-            _    => return ,
-        }
-    }
-
-    fn left_nib_is_b(&mut self, rn: u8)
-    {
-        match rn
-        {
-            0x00 => self.bcs(),
-            0x01 => self.lda_indirect_y(),
-            0x02 => return,
-            0x03 => return ,
-            0x04 => self.ldy_zero_page_x(),
-            0x05 => self.lda_zero_page_x(),
-            0x06 => self.ldx_zero_page_y(),
-            0x07 => return ,
-            0x08 => return ,
-            0x09 => self.lda_absolute_y(),
-            0x0A => return ,
-            0x0B => return ,
-            0x0C => self.ldy_absolute_x(),
-            0x0D => self.lda_absolute_x(),
-            0x0E => self.ldx_absolute_y(),
-            0x0F => return ,
-            _    => return ,
-        }
-    }
-
-    fn left_nib_is_c(&mut self, rn: u8)
-    {
-        match rn
-        {
-            0x00 => return,
-            0x01 => return ,
-            0x02 => return,
-            0x03 => return ,
-            0x04 => return,
-            0x05 => return ,
-            0x06 => return ,
-            0x07 => return ,
-            0x08 => self.iny(),
-            0x09 => self.cmp_immediate(),
-            0x0A => self.dex(),
-            0x0B => return ,
-            0x0C => return ,
-            0x0D => return,
-            0x0E => return ,
-            0x0F => return ,
-            _    => return ,
-        }
-    }
-
-    fn left_nib_is_d(&mut self, rn: u8)
-    {
-        match rn
-        {
-            0x00 => return ,
-            0x01 => return,
-            0x02 => return ,
-            0x03 => return ,
-            0x04 => return ,
-            0x05 => return ,
-            0x06 => return ,
-            0x07 => return ,
-            0x08 => self.cld(),
-            0x09 => return ,
-            0x0A => return ,
-            0x0B => return ,
-            0x0C => return ,
-            0x0D => return ,
-            0x0E => return ,
-            0x0F => return ,
-            _    => return ,
-        }
-    }
-
-    fn left_nib_is_e(&mut self, rn: u8)
-    {
-        match rn
-        {
-            0x00 => return,
-            0x01 => return ,
-            0x02 => return,
-            0x03 => return ,
-            0x04 => return,
-            0x05 => return ,
-            0x06 => return ,
-            0x07 => return ,
-            0x08 => self.inx(),
-            0x09 => return,
-            0x0A => return ,
-            0x0B => return ,
-            0x0C => return ,
-            0x0D => return,
-            0x0E => return ,
-            0x0F => return ,
-            _    => return ,
-        }
-    }
-
-    fn left_nib_is_f(&mut self, rn: u8)
-    {
-        match rn
-        {
-            0x00 => return,
-            0x01 => return ,
-            0x02 => return,
-            0x03 => return ,
-            0x04 => return,
-            0x05 => return ,
-            0x06 => return ,
-            0x07 => return ,
-            0x08 => return ,
-            0x09 => return,
-            0x0A => return ,
-            0x0B => return ,
-            0x0C => return ,
-            0x0D => return,
-            0x0E => return ,
-            0x0F => return ,
-            _    => return ,
+    fn update_zero_and_negative_flags(&mut self, val: u8)
+    {
+        if val == 0
+        {
+            self.set_zero_flag();
+        }
+        else
+        {
+            self.reset_zero_flag();
+        }
+
+        if val & 0b10000000 != 0
+        {
+            self.set_negative_flag();
+        }
+        else
+        {
+            self.reset_negative_flag();
         }
     }
 
+    pub fn get_bus(&self) -> &B
+    {
+        return &self.bus;
+    }
 
-    ////////////////////////////////////////////////////
-    ////////////////////////////////////////////////////
-    // INSTRUCTIONS //
-    ////////////////////////////////////////////////////
-    ////////////////////////////////////////////////////
-    fn brk(&mut self) // 0x00
+    pub fn get_bus_mut(&mut self) -> &mut B
     {
-        self.increment_pc(2);
+        return &mut self.bus;
     }
 
-    fn ora_indirect_x(&mut self) // 0x01
+    fn read(&mut self, address: u16) -> u8
     {
-        self.x += self.first_byte_of_interest & 0xFF;//wraps around zero page
-        self.a |= self.memory[self.x as usize] as u8;
-        self.increment_pc(2);
+        return self.bus.read(address);
     }
 
-    fn and_indirect_y(&mut self) // 0x31
+    fn write(&mut self, address: u16, val: u8)
     {
-        self.y += self.memory[self.first_byte_of_interest as usize] as u8; // does not need to wrap
-        self.a &= self.y;
-        self.increment_pc(2);
+        self.bus.write(address, val);
     }
 
-    fn sei(&mut self) // 0x78
+    fn read_word(&mut self, address: u16) -> u16
     {
-        self.set_interrupt_flag();
-        self.increment_pc(1);
+        let low = self.read(address) as u16;
+        let high = self.read(address.wrapping_add(1)) as u16;
+        return (high << 8) | low;
     }
 
-    fn cld(&mut self) // 0xD8
+    // the stack grows down and wraps within page 0x01
+    fn push(&mut self, val: u8)
     {
-        self.reset_decimal_flag();
-        self.increment_pc(1);
+        let address = STACK_PAGE | self.s as u16;
+        self.write(address, val);
+        self.s = self.s.wrapping_sub(1);
     }
 
-    fn lda_immediate(&mut self) // 0xA9
+    fn pull(&mut self) -> u8
     {
-        self.a = self.first_byte_of_interest;
-        self.increment_pc(2);
+        self.s = self.s.wrapping_add(1);
+        let address = STACK_PAGE | self.s as u16;
+        return self.read(address);
     }
 
-    fn lda_zero_page(&mut self) // 0xA5
+
+    ////////////////////////////////////////////////////
+    ////////////////////////////////////////////////////
+    // ADDRESSING MODES //
+    ////////////////////////////////////////////////////
+    ////////////////////////////////////////////////////
+    fn zero_page_address(&self) -> u16
     {
-        self.a = self.memory[self.first_byte_of_interest as usize] as u8;
-        self.increment_pc(2);
+        return self.first_byte_of_interest as u16;
     }
 
-    fn lda_zero_page_x(&mut self) // 0xB5
+    fn zero_page_x_address(&self) -> u16
     {
-        let temp_address = self.first_byte_of_interest + self.x;
+        return self.first_byte_of_interest.wrapping_add(self.x) as u16; // wraps around zero page
+    }
 
-        self.a = self.memory[temp_address as usize] as u8;
-        self.increment_pc(2);
+    fn zero_page_y_address(&self) -> u16
+    {
+        return self.first_byte_of_interest.wrapping_add(self.y) as u16; // wraps around zero page
     }
 
-    fn lda_absolute(&mut self) // 0xAD
+    fn absolute_address(&self) -> u16
     {
         let mut temp_address: u16 = 0x0000;
         temp_address |= self.second_byte_of_interest as u16;
         temp_address <<= 8;
         temp_address |= self.first_byte_of_interest as u16;
-        self.a = self.memory[temp_address as usize] as u8;
-        self.increment_pc(3);
+        return temp_address;
     }
 
-    fn lda_absolute_x(&mut self) // 0xBD
+    // indexed reads take an extra cycle when the high byte has to be fixed up
+    fn index_address(&mut self, base: u16, index: u8) -> u16
     {
-        let mut temp_address: u16 = 0x0000;
-        temp_address |= self.second_byte_of_interest as u16;
-        temp_address <<= 8;
-        temp_address |= self.first_byte_of_interest as u16;
-        temp_address += self.x as u16;
-        self.a = self.memory[temp_address as usize] as u8;
-        self.increment_pc(3);
+        let address = base.wrapping_add(index as u16);
+        self.page_crossed = (base & 0xFF00) != (address & 0xFF00);
+        return address;
     }
 
-    fn lda_absolute_y(&mut self) // 0xB9
+    fn absolute_x_address(&mut self) -> u16
     {
-        let mut temp_address: u16 = 0x0000;
-        temp_address |= self.second_byte_of_interest as u16;
-        temp_address <<= 8;
-        temp_address |= self.first_byte_of_interest as u16;
-        temp_address += self.y as u16;
-        self.a = self.memory[temp_address as usize] as u8;
-        self.increment_pc(3);
+        let base = self.absolute_address();
+        return self.index_address(base, self.x);
     }
 
-    fn lda_indirect_x(&mut self) // 0xA1
+    fn absolute_y_address(&mut self) -> u16
     {
-        self.x += self.first_byte_of_interest & 0xFF;
-        self.a = self.memory[self.x as usize] as u8;
-        self.increment_pc(2);
+        let base = self.absolute_address();
+        return self.index_address(base, self.y);
     }
 
-    fn lda_indirect_y(&mut self) // 0xB1
+    // pointers in zero page never leave it, a pointer at 0xFF takes
+    // its high byte from 0x00 rather than 0x100
+    fn read_zero_page_word(&mut self, pointer: u8) -> u16
     {
-        self.y += self.memory[self.first_byte_of_interest as usize] as u8; // does not need to wrap
-        self.a = self.y;
-        self.increment_pc(2);
+        let low = self.read(pointer as u16) as u16;
+        let high = self.read(pointer.wrapping_add(1) as u16) as u16;
+        return (high << 8) | low;
     }
 
-    fn ldx_immediate(&mut self) // 0xA2
+    // ($zz,X): X is added to the operand before the pointer is read
+    fn indirect_x_address(&mut self) -> u16
     {
-        self.x = self.first_byte_of_interest;
-        self.increment_pc(2);
+        let pointer = self.first_byte_of_interest.wrapping_add(self.x);
+        return self.read_zero_page_word(pointer);
     }
 
-    fn ldx_zero_page(&mut self) // 0xA6
+    // ($zz),Y: the pointer is read first and Y is added to the address it holds
+    fn indirect_y_address(&mut self) -> u16
     {
-        self.x = self.memory[self.first_byte_of_interest as usize] as u8;
-        self.increment_pc(2);
+        let pointer = self.first_byte_of_interest;
+        let base = self.read_zero_page_word(pointer);
+        return self.index_address(base, self.y);
     }
 
-    fn ldx_zero_page_y(&mut self) // 0xB6
+    // JMP ($xxxx) resolves to the address stored at the pointer
+    fn indirect_address(&mut self) -> u16
     {
-        let temp_address = self.first_byte_of_interest + self.y;
+        // the 6502 does not carry into the high byte when fetching the pointer,
+        // so JMP ($xxFF) reads its high byte from $xx00
+        let pointer = self.absolute_address();
+        let high_pointer = (pointer & 0xFF00) | (pointer.wrapping_add(1) & 0x00FF);
+        let low = self.read(pointer) as u16;
+        let high = self.read(high_pointer) as u16;
+        return (high << 8) | low;
+    }
 
-        self.x = self.memory[temp_address as usize] as u8;
-        self.increment_pc(2);
+    fn operand_address(&mut self, mode: AddressingMode) -> u16
+    {
+        match mode
+        {
+            AddressingMode::ZeroPage  => self.zero_page_address(),
+            AddressingMode::ZeroPageX => self.zero_page_x_address(),
+            AddressingMode::ZeroPageY => self.zero_page_y_address(),
+            AddressingMode::Absolute  => self.absolute_address(),
+            AddressingMode::AbsoluteX => self.absolute_x_address(),
+            AddressingMode::AbsoluteY => self.absolute_y_address(),
+            AddressingMode::Indirect  => self.indirect_address(),
+            AddressingMode::IndirectX => self.indirect_x_address(),
+            AddressingMode::IndirectY => self.indirect_y_address(),
+            _ => panic!("{:?} addressing has no operand address", mode),
+        }
     }
 
-    fn ldx_absolute(&mut self) // 0xAE
+    fn read_operand(&mut self, mode: AddressingMode) -> u8
     {
-        let mut temp_address: u16 = 0x0000;
-        temp_address |= self.second_byte_of_interest as u16;
-        temp_address <<=8;
-        temp_address |= self.first_byte_of_interest as u16;
-        self.x = self.memory[temp_address as usize] as u8;
-        self.increment_pc(3);
+        match mode
+        {
+            AddressingMode::Immediate   => self.first_byte_of_interest,
+            AddressingMode::Accumulator => self.a,
+            _ =>
+            {
+                let address = self.operand_address(mode);
+                return self.read(address);
+            },
+        }
     }
 
-    fn ldx_absolute_y(&mut self) // 0xBE
+    // read-modify-write instructions operate on either A or memory
+    // returns the value written back
+    fn modify(&mut self, mode: AddressingMode, operation: fn(&mut CPU<B>, u8) -> u8) -> u8
     {
-        let mut temp_address: u16 = 0x0000;
-        temp_address |= self.second_byte_of_interest as u16;
-        temp_address <<= 8;
-        temp_address |= self.first_byte_of_interest as u16;
-        temp_address += self.y as u16;
-        self.x = self.memory[temp_address as usize] as u8;
-        self.increment_pc(3);
+        if mode == AddressingMode::Accumulator
+        {
+            let value = self.a;
+            self.a = operation(self, value);
+            return self.a;
+        }
+
+        let address = self.operand_address(mode);
+        let value = self.read(address);
+        let result = operation(self, value);
+        self.write(address, result);
+        return result;
+    }
+
+    // SHX, SHY, AHX and TAS store val ANDed with the high byte of the base
+    // address plus one, and on a page cross that value replaces the high byte
+    fn unstable_store(&mut self, mode: AddressingMode, val: u8)
+    {
+        let (base, index) = match mode
+        {
+            AddressingMode::AbsoluteX => (self.absolute_address(), self.x),
+            AddressingMode::AbsoluteY => (self.absolute_address(), self.y),
+            _ =>
+            {
+                let pointer = self.first_byte_of_interest;
+                (self.read_zero_page_word(pointer), self.y)
+            },
+        };
+        let mut address = base.wrapping_add(index as u16);
+        let result = val & ((base >> 8) as u8).wrapping_add(1);
+        if (base & 0xFF00) != (address & 0xFF00)
+        {
+            address = ((result as u16) << 8) | (address & 0x00FF);
+        }
+        self.write(address, result);
+    }
+
+
+    ////////////////////////////////////////////////////
+    ////////////////////////////////////////////////////
+    // OPERATIONS //
+    ////////////////////////////////////////////////////
+    ////////////////////////////////////////////////////
+    fn decimal_mode(&self) -> bool
+    {
+        return self.variant == Variant::Nmos6502 && self.get_decimal_flag() != 0;
     }
 
-    fn ldy_immediate(&mut self) // 0xA0
+    fn adc(&mut self, val: u8)
     {
-        self.y = self.first_byte_of_interest;
-        self.increment_pc(2);
+        if self.decimal_mode()
+        {
+            self.adc_decimal(val);
+        }
+        else
+        {
+            self.adc_binary(val);
+        }
     }
 
-    fn ldy_zero_page(&mut self) // 0xA4
+    fn sbc(&mut self, val: u8)
     {
-        self.y = self.memory[self.first_byte_of_interest as usize] as u8;
-        self.increment_pc(2);
+        if self.decimal_mode()
+        {
+            self.sbc_decimal(val);
+        }
+        else
+        {
+            // A - M - (1 - C) is the same as A + !M + C
+            self.adc_binary(!val);
+        }
     }
 
-    fn ldy_zero_page_x(&mut self) // 0xB4
+    fn adc_binary(&mut self, val: u8)
     {
-        let temp_address = self.first_byte_of_interest + self.x;
+        let sum = self.a as u16 + val as u16 + self.get_carry_flag() as u16;
+        let result = sum as u8;
+
+        if sum > 0xFF
+        {
+            self.set_carry_flag();
+        }
+        else
+        {
+            self.reset_carry_flag();
+        }
+
+        // overflow when both inputs share a sign that the result does not
+        if (self.a ^ result) & (val ^ result) & 0b10000000 != 0
+        {
+            self.set_overflow_flag();
+        }
+        else
+        {
+            self.reset_overflow_flag();
+        }
 
-        self.y = self.memory[temp_address as usize] as u8;
-        self.increment_pc(2);
+        self.a = result;
+        self.update_zero_and_negative_flags(result);
     }
 
-    fn ldy_absolute(&mut self) // 0xAC
+    // NMOS BCD addition: Z still reflects the binary sum, while N and V
+    // are taken after the low digit is adjusted but before the high digit is
+    fn adc_decimal(&mut self, val: u8)
     {
-        let mut temp_address: u16 = 0x0000;
-        temp_address |= self.second_byte_of_interest as u16;
-        temp_address <<= 8;
-        temp_address |= self.first_byte_of_interest as u16;
-        self.y = self.memory[temp_address as usize] as u8;
-        self.increment_pc(3);
+        let a = self.a as u16;
+        let m = val as u16;
+        let carry = self.get_carry_flag() as u16;
+
+        let binary = (a + m + carry) & 0xFF;
+        let mut low = (a & 0x0F) + (m & 0x0F) + carry;
+        if low > 0x09
+        {
+            low += 0x06;
+        }
+        let mut high = (a & 0xF0) + (m & 0xF0);
+        if low > 0x0F
+        {
+            high += 0x10;
+        }
+
+        self.update_zero_and_negative_flags(binary as u8);
+        if high & 0x80 != 0
+        {
+            self.set_negative_flag();
+        }
+        else
+        {
+            self.reset_negative_flag();
+        }
+        if (a ^ high) & (m ^ high) & 0x80 != 0
+        {
+            self.set_overflow_flag();
+        }
+        else
+        {
+            self.reset_overflow_flag();
+        }
+
+        if high > 0x90
+        {
+            high += 0x60;
+        }
+        if high > 0xFF
+        {
+            self.set_carry_flag();
+        }
+        else
+        {
+            self.reset_carry_flag();
+        }
+
+        self.a = ((high & 0xF0) | (low & 0x0F)) as u8;
     }
 
-    fn ldy_absolute_x(&mut self) // 0xBC
+    // NMOS BCD subtraction: every flag matches the binary subtraction,
+    // only the value left in A is decimal adjusted
+    fn sbc_decimal(&mut self, val: u8)
     {
-        let mut temp_address: u16 = 0x0000;
-        temp_address |= self.second_byte_of_interest as u16;
-        temp_address <<= 8;
-        temp_address |= self.first_byte_of_interest as u16;
-        temp_address += self.x as u16;
-        self.y = self.memory[temp_address as usize] as u8;
-        self.increment_pc(3);
+        let a = self.a as i16;
+        let m = val as i16;
+        let carry = self.get_carry_flag() as i16;
+
+        let mut low = (a & 0x0F) - (m & 0x0F) + carry - 1;
+        if low < 0
+        {
+            low = ((low - 0x06) & 0x0F) - 0x10;
+        }
+        let mut result = (a & 0xF0) - (m & 0xF0) + low;
+        if result < 0
+        {
+            result -= 0x60;
+        }
+
+        self.adc_binary(!val);
+        self.a = (result & 0xFF) as u8;
     }
 
-    fn sta_absolute(&mut self) // 0x8D
+    fn and(&mut self, val: u8)
     {
-        let mut temp_address: u16 = 0x0000;
-        temp_address |= self.second_byte_of_interest as u16;
-        temp_address <<= 8;
-        temp_address |= self.first_byte_of_interest as u16;
-        self.memory[temp_address as usize] = self.a as u16;
-        self.increment_pc(3);
+        self.a &= val;
+        let result = self.a;
+        self.update_zero_and_negative_flags(result);
     }
 
-    fn txs(&mut self) // 0x9A
+    fn ora(&mut self, val: u8)
     {
-        self.s = self.stack_offset + self.x as u16;
-        self.increment_pc(1);
+        self.a |= val;
+        let result = self.a;
+        self.update_zero_and_negative_flags(result);
     }
 
-    fn bpl(&mut self) // 0x10
+    fn eor(&mut self, val: u8)
     {
-        if self.get_negative_flag() == 0b10000000
+        self.a ^= val;
+        let result = self.a;
+        self.update_zero_and_negative_flags(result);
+    }
+
+    fn compare(&mut self, register: u8, val: u8)
+    {
+        if register >= val
         {
-            self.increment_pc(2);
+            self.set_carry_flag();
         }
         else
         {
-            self.pc += self.first_byte_of_interest as u16;
+            self.reset_carry_flag();
         }
+        // N is bit 7 of the difference, not the result of the unsigned comparison
+        self.update_zero_and_negative_flags(register.wrapping_sub(val));
     }
 
-    fn bcs(&mut self) // 0xB0
+    fn bit(&mut self, val: u8)
     {
-        if self.get_carry_flag() == 0b00000001
+        if self.a & val == 0
         {
-            self.pc += self.first_byte_of_interest as u16;
+            self.set_zero_flag();
         }
         else
         {
-            self.increment_pc(2);
+            self.reset_zero_flag();
         }
+
+        // N and V are copied straight from bits 7 and 6 of the operand
+        self.p = (self.p & 0b00111111) | (val & 0b11000000);
     }
 
-    fn cmp_immediate(&mut self) // 0xC9
+    fn shift_left(&mut self, val: u8) -> u8
     {
-        if self.get_a() > self.first_byte_of_interest
+        if val & 0b10000000 != 0
         {
             self.set_carry_flag();
-            self.reset_zero_flag();
-            self.reset_negative_flag();
-
         }
-        else if self.get_a() == self.first_byte_of_interest
+        else
+        {
+            self.reset_carry_flag();
+        }
+        let result = val << 1;
+        self.update_zero_and_negative_flags(result);
+        return result;
+    }
+
+    fn shift_right(&mut self, val: u8) -> u8
+    {
+        if val & 0b00000001 != 0
         {
             self.set_carry_flag();
-            self.set_zero_flag();
-            self.reset_negative_flag();
         }
-        else if self.get_a() < self.first_byte_of_interest
+        else
         {
-            self.set_negative_flag();
             self.reset_carry_flag();
-            self.reset_zero_flag();
         }
-        self.increment_pc(2);
+        let result = val >> 1;
+        self.update_zero_and_negative_flags(result);
+        return result;
+    }
+
+    fn rotate_left(&mut self, val: u8) -> u8
+    {
+        let carry_in = self.get_carry_flag();
+        let result = self.shift_left(val) | carry_in;
+        self.update_zero_and_negative_flags(result);
+        return result;
+    }
+
+    fn rotate_right(&mut self, val: u8) -> u8
+    {
+        let carry_in = self.get_carry_flag() << 7;
+        let result = self.shift_right(val) | carry_in;
+        self.update_zero_and_negative_flags(result);
+        return result;
+    }
+
+    fn increment(&mut self, val: u8) -> u8
+    {
+        let result = val.wrapping_add(1);
+        self.update_zero_and_negative_flags(result);
+        return result;
+    }
+
+    fn decrement(&mut self, val: u8) -> u8
+    {
+        let result = val.wrapping_sub(1);
+        self.update_zero_and_negative_flags(result);
+        return result;
+    }
+
+    // the pc already points at the instruction following the branch
+    fn branch(&mut self, condition: bool)
+    {
+        if condition
+        {
+            let target = branch_target(self.pc, self.first_byte_of_interest);
+
+            // one cycle for taking the branch, another if it lands on a new page
+            self.extra_cycles += 1;
+            if (target & 0xFF00) != (self.pc & 0xFF00)
+            {
+                self.extra_cycles += 1;
+            }
+            self.pc = target;
+        }
+    }
+
+    // Power up / reset line: the pc comes from the reset vector,
+    // S ends up at 0xFD and interrupts are disabled.
+    pub fn reset(&mut self)
+    {
+        self.halted = false;
+        self.s = 0xFD;
+        self.set_interrupt_flag();
+        self.nmi_pending = false;
+        self.irq_disabled_at_poll = true;
+        self.poll_skipped = false;
+        self.early_poll_due = false;
+        self.pc = self.read_word(RESET_VECTOR);
+        self.cycles += 7;
+        self.bus.tick(7);
+    }
+
+    // NMI is edge triggered, holding the line low does not raise another one
+    pub fn set_nmi_line(&mut self, level: bool)
+    {
+        if level && !self.nmi_line
+        {
+            self.nmi_pending = true;
+        }
+        self.nmi_line = level;
+    }
+
+    pub fn get_nmi_line(&self) -> bool
+    {
+        return self.nmi_line;
+    }
+
+    // IRQ is level triggered, it keeps firing while the line is held and I is clear
+    pub fn set_irq_line(&mut self, level: bool)
+    {
+        self.irq_line = level;
+    }
+
+    pub fn get_irq_line(&self) -> bool
+    {
+        return self.irq_line;
+    }
+
+    // push the return address and status, then jump through the vector
+    fn interrupt(&mut self, vector: u16, from_brk: bool)
+    {
+        let return_address = self.pc;
+        self.push((return_address >> 8) as u8);
+        self.push((return_address & 0xFF) as u8);
+
+        let mut status = self.p | 0b00100000;
+        if from_brk
+        {
+            status |= 0b00010000;
+        }
+        else
+        {
+            status &= 0b11101111;
+        }
+        self.push(status);
+
+        self.set_interrupt_flag();
+        self.pc = self.read_word(vector);
+    }
+
+    // The lines are changed between steps by whatever ran alongside the last
+    // instruction, so they are checked against that instruction's poll.
+    fn interrupt_due(&self) -> bool
+    {
+        if self.poll_skipped
+        {
+            return self.early_poll_due;
+        }
+        return self.lines_due();
+    }
+
+    fn lines_due(&self) -> bool
+    {
+        // IRQ is wired-OR, any device on the bus can pull it low
+        let irq = self.irq_line || self.bus.irq_line();
+        return self.nmi_pending || (irq && !self.irq_disabled_at_poll);
+    }
+
+    // an interrupt sequence takes as long as a BRK, an NMI arriving
+    // before the vector is fetched takes over an IRQ sequence
+    fn service_interrupt(&mut self) -> u16
+    {
+        let vector = if self.nmi_pending { NMI_VECTOR } else { IRQ_VECTOR };
+        self.nmi_pending = false;
+        self.interrupt(vector, false);
+
+        // the first instruction of the handler always runs
+        self.poll_skipped = true;
+        self.early_poll_due = false;
+        self.cycles += 7;
+        return 7;
+    }
+
+    // fetch the instruction at the pc along with its operand bytes, then run it
+    // returns the number of cycles the instruction took, which the bus is ticked for
+    pub fn step(&mut self) -> u16
+    {
+        if self.halted
+        {
+            return 0;
+        }
+        if self.interrupt_due()
+        {
+            let cycles = self.service_interrupt();
+            self.bus.tick(cycles);
+            return cycles;
+        }
+
+        let pc = self.pc;
+        let code = self.read(pc);
+        let entry = decode(code);
+
+        self.instruction = code;
+        if entry.length > 1
+        {
+            self.first_byte_of_interest = self.read(pc.wrapping_add(1));
+        }
+        if entry.length > 2
+        {
+            self.second_byte_of_interest = self.read(pc.wrapping_add(2));
+        }
+        let cycles = self.execute_opcode(entry);
+        // let everything clocked alongside the CPU catch up
+        self.bus.tick(cycles);
+        return cycles;
+    }
+
+    pub fn execute_opcode(&mut self, entry: OpcodeEntry) -> u16
+    {
+        let mode = entry.mode;
+        self.extra_cycles = 0;
+        self.page_crossed = false;
+        let interrupt_flag = self.get_interrupt_flag();
+
+        // operands are already latched, so step past the whole instruction
+        // up front and let control flow instructions overwrite the pc
+        self.increment_pc(entry.length as u16);
+
+        match entry.mnemonic
+        {
+            Mnemonic::Adc => { let value = self.read_operand(mode); self.adc(value); },
+            Mnemonic::And => { let value = self.read_operand(mode); self.and(value); },
+            Mnemonic::Asl => { self.modify(mode, Self::shift_left); },
+            Mnemonic::Bcc => { let condition = self.get_carry_flag() == 0; self.branch(condition); },
+            Mnemonic::Bcs => { let condition = self.get_carry_flag() != 0; self.branch(condition); },
+            Mnemonic::Beq => { let condition = self.get_zero_flag() != 0; self.branch(condition); },
+            Mnemonic::Bit => { let value = self.read_operand(mode); self.bit(value); },
+            Mnemonic::Bmi => { let condition = self.get_negative_flag() != 0; self.branch(condition); },
+            Mnemonic::Bne => { let condition = self.get_zero_flag() == 0; self.branch(condition); },
+            Mnemonic::Bpl => { let condition = self.get_negative_flag() == 0; self.branch(condition); },
+            Mnemonic::Brk => self.brk(),
+            Mnemonic::Bvc => { let condition = self.get_overflow_flag() == 0; self.branch(condition); },
+            Mnemonic::Bvs => { let condition = self.get_overflow_flag() != 0; self.branch(condition); },
+            Mnemonic::Clc => self.reset_carry_flag(),
+            Mnemonic::Cld => self.reset_decimal_flag(),
+            Mnemonic::Cli => self.reset_interrupt_flag(),
+            Mnemonic::Clv => self.reset_overflow_flag(),
+            Mnemonic::Cmp => { let value = self.read_operand(mode); let a = self.a; self.compare(a, value); },
+            Mnemonic::Cpx => { let value = self.read_operand(mode); let x = self.x; self.compare(x, value); },
+            Mnemonic::Cpy => { let value = self.read_operand(mode); let y = self.y; self.compare(y, value); },
+            Mnemonic::Dec => { self.modify(mode, Self::decrement); },
+            Mnemonic::Dex => self.dex(),
+            Mnemonic::Dey => self.dey(),
+            Mnemonic::Eor => { let value = self.read_operand(mode); self.eor(value); },
+            Mnemonic::Inc => { self.modify(mode, Self::increment); },
+            Mnemonic::Inx => self.inx(),
+            Mnemonic::Iny => self.iny(),
+            Mnemonic::Jmp => self.pc = self.operand_address(mode),
+            Mnemonic::Jsr => self.jsr(),
+            Mnemonic::Lda => { let value = self.read_operand(mode); self.lda(value); },
+            Mnemonic::Ldx => { let value = self.read_operand(mode); self.ldx(value); },
+            Mnemonic::Ldy => { let value = self.read_operand(mode); self.ldy(value); },
+            Mnemonic::Lsr => { self.modify(mode, Self::shift_right); },
+            Mnemonic::Nop => self.nop(mode),
+            Mnemonic::Ora => { let value = self.read_operand(mode); self.ora(value); },
+            Mnemonic::Pha => self.pha(),
+            Mnemonic::Php => self.php(),
+            Mnemonic::Pla => self.pla(),
+            Mnemonic::Plp => self.plp(),
+            Mnemonic::Rol => { self.modify(mode, Self::rotate_left); },
+            Mnemonic::Ror => { self.modify(mode, Self::rotate_right); },
+            Mnemonic::Rti => self.rti(),
+            Mnemonic::Rts => self.rts(),
+            Mnemonic::Sbc => { let value = self.read_operand(mode); self.sbc(value); },
+            Mnemonic::Sec => self.set_carry_flag(),
+            Mnemonic::Sed => self.set_decimal_flag(),
+            Mnemonic::Sei => self.set_interrupt_flag(),
+            Mnemonic::Sta => { let address = self.operand_address(mode); let a = self.a; self.write(address, a); },
+            Mnemonic::Stx => { let address = self.operand_address(mode); let x = self.x; self.write(address, x); },
+            Mnemonic::Sty => { let address = self.operand_address(mode); let y = self.y; self.write(address, y); },
+            Mnemonic::Tax => self.tax(),
+            Mnemonic::Tay => self.tay(),
+            Mnemonic::Tsx => self.tsx(),
+            Mnemonic::Txa => self.txa(),
+            Mnemonic::Txs => self.txs(),
+            Mnemonic::Tya => self.tya(),
+            Mnemonic::Slo => { let value = self.modify(mode, Self::shift_left); self.ora(value); },
+            Mnemonic::Rla => { let value = self.modify(mode, Self::rotate_left); self.and(value); },
+            Mnemonic::Sre => { let value = self.modify(mode, Self::shift_right); self.eor(value); },
+            Mnemonic::Rra => { let value = self.modify(mode, Self::rotate_right); self.adc(value); },
+            Mnemonic::Sax => { let address = self.operand_address(mode); let value = self.a & self.x; self.write(address, value); },
+            Mnemonic::Lax => { let value = self.read_operand(mode); self.lax(value); },
+            Mnemonic::Dcp => { let value = self.modify(mode, Self::decrement); let a = self.a; self.compare(a, value); },
+            Mnemonic::Isc => { let value = self.modify(mode, Self::increment); self.sbc(value); },
+            Mnemonic::Anc => { let value = self.read_operand(mode); self.anc(value); },
+            Mnemonic::Alr => { let value = self.read_operand(mode); self.alr(value); },
+            Mnemonic::Arr => { let value = self.read_operand(mode); self.arr(value); },
+            Mnemonic::Axs => { let value = self.read_operand(mode); self.axs(value); },
+            Mnemonic::Xaa => { let value = self.read_operand(mode); self.xaa(value); },
+            Mnemonic::Lxa => { let value = self.read_operand(mode); self.lxa(value); },
+            Mnemonic::Ahx => { let value = self.a & self.x; self.unstable_store(mode, value); },
+            Mnemonic::Tas => { self.s = self.a & self.x; let value = self.s; self.unstable_store(mode, value); },
+            Mnemonic::Shy => { let value = self.y; self.unstable_store(mode, value); },
+            Mnemonic::Shx => { let value = self.x; self.unstable_store(mode, value); },
+            Mnemonic::Las => { let value = self.read_operand(mode); self.las(value); },
+            Mnemonic::Kil => self.kil(),
+        }
+
+        // Interrupts are polled before the last cycle, so CLI, SEI and PLP only
+        // change I after the poll and their effect is one instruction late.
+        // RTI restores P early enough for its own poll.
+        self.irq_disabled_at_poll = match entry.mnemonic
+        {
+            Mnemonic::Cli | Mnemonic::Sei | Mnemonic::Plp => interrupt_flag != 0,
+            _ => self.get_interrupt_flag() != 0,
+        };
+        // a taken branch that stays on its page skips the poll on its last
+        // cycle, so an interrupt arriving during it waits one more instruction.
+        // It still polls on its second cycle, which sees the lines as they
+        // were before the step, so anything already asserted is taken after it.
+        self.poll_skipped = entry.mode == AddressingMode::Relative && self.extra_cycles == 1;
+        self.early_poll_due = self.poll_skipped && self.lines_due();
+
+        let mut cycles = entry.cycles as u16 + self.extra_cycles;
+        if entry.page_cross_penalty && self.page_crossed
+        {
+            cycles += 1;
+        }
+        self.cycles += cycles as u64;
+
+        // OAM DMA halts the CPU, with an alignment cycle when started on an odd cycle
+        let mut stall = self.bus.take_stall_cycles();
+        if stall > 0
+        {
+            stall += (self.cycles % 2) as u16;
+            cycles += stall;
+            self.cycles += stall as u64;
+        }
+        return cycles;
+    }
+
+
+    ////////////////////////////////////////////////////
+    ////////////////////////////////////////////////////
+    // INSTRUCTIONS //
+    ////////////////////////////////////////////////////
+    ////////////////////////////////////////////////////
+    fn brk(&mut self) // 0x00
+    {
+        self.increment_pc(1); // BRK skips the padding byte after it
+
+        // an NMI arriving during BRK hijacks it, B is still pushed as set
+        let vector = if self.nmi_pending { NMI_VECTOR } else { IRQ_VECTOR };
+        self.nmi_pending = false;
+        self.interrupt(vector, true);
+    }
+
+    fn lda(&mut self, val: u8)
+    {
+        self.a = val;
+        self.update_zero_and_negative_flags(val);
+    }
+
+    fn ldx(&mut self, val: u8)
+    {
+        self.x = val;
+        self.update_zero_and_negative_flags(val);
+    }
+
+    fn ldy(&mut self, val: u8)
+    {
+        self.y = val;
+        self.update_zero_and_negative_flags(val);
+    }
+
+    fn txs(&mut self) // 0x9A, the only transfer that leaves the flags alone
+    {
+        self.s = self.x;
+    }
+
+    fn tsx(&mut self) // 0xBA
+    {
+        self.x = self.s;
+        let value = self.x;
+        self.update_zero_and_negative_flags(value);
     }
 
     fn tax(&mut self) //0xAA
     {
         self.x = self.a;
-        self.increment_pc(1);
+        let value = self.x;
+        self.update_zero_and_negative_flags(value);
     }
 
     fn txa(&mut self) //0x8A
     {
         self.a = self.x;
-        self.increment_pc(1);
+        let value = self.a;
+        self.update_zero_and_negative_flags(value);
     }
 
     fn dex(&mut self) // 0xCA
     {
-        self.x -= 0x01;
-        self.increment_pc(1);
+        self.x = self.x.wrapping_sub(0x01);
+        let value = self.x;
+        self.update_zero_and_negative_flags(value);
     }
 
     fn inx(&mut self) // 0xE8
     {
-        self.x += 0x01;
-        self.increment_pc(1);
+        self.x = self.x.wrapping_add(0x01);
+        let value = self.x;
+        self.update_zero_and_negative_flags(value);
     }
 
     fn tay(&mut self) // 0xA8
     {
         self.y = self.a;
-        self.increment_pc(1);
+        let value = self.y;
+        self.update_zero_and_negative_flags(value);
     }
 
     fn tya(&mut self) // 0x98
     {
         self.a = self.y;
-        self.increment_pc(1);
+        let value = self.a;
+        self.update_zero_and_negative_flags(value);
     }
 
     fn dey(&mut self) // 0x88
     {
-        self.y -= 0x01;
-        self.increment_pc(1);
+        self.y = self.y.wrapping_sub(0x01);
+        let value = self.y;
+        self.update_zero_and_negative_flags(value);
     }
 
     fn iny(&mut self) // 0xC8
     {
-        self.y += 0x01;
-        self.increment_pc(1);
+        self.y = self.y.wrapping_add(0x01);
+        let value = self.y;
+        self.update_zero_and_negative_flags(value);
+    }
+
+    fn pha(&mut self) // 0x48
+    {
+        let value = self.a;
+        self.push(value);
+    }
+
+    fn php(&mut self) // 0x08
+    {
+        // the break flag and bit 5 are always set in the pushed copy
+        let value = self.p | 0b00110000;
+        self.push(value);
+    }
+
+    fn pla(&mut self) // 0x68
+    {
+        self.a = self.pull();
+        let value = self.a;
+        self.update_zero_and_negative_flags(value);
+    }
+
+    fn plp(&mut self) // 0x28
+    {
+        // the break flag does not exist in the register itself
+        let value = self.pull();
+        self.p = (value & 0b11001111) | 0b00100000;
+    }
+
+    fn jsr(&mut self) // 0x20
+    {
+        // the pushed return address points at the last byte of the JSR
+        let return_address = self.pc.wrapping_sub(1);
+        self.push((return_address >> 8) as u8);
+        self.push((return_address & 0xFF) as u8);
+        self.pc = self.absolute_address();
+    }
+
+    fn rts(&mut self) // 0x60
+    {
+        let low = self.pull() as u16;
+        let high = self.pull() as u16;
+        self.pc = ((high << 8) | low).wrapping_add(1);
+    }
+
+    fn rti(&mut self) // 0x40
+    {
+        let value = self.pull();
+        self.p = (value & 0b11001111) | 0b00100000;
+        let low = self.pull() as u16;
+        let high = self.pull() as u16;
+        self.pc = (high << 8) | low;
+    }
+
+    fn nop(&mut self, mode: AddressingMode)
+    {
+        // the unofficial NOPs with operands still perform the read
+        if mode != AddressingMode::Implied
+        {
+            self.read_operand(mode);
+        }
+    }
+
+    fn kil(&mut self)
+    {
+        // leave the pc on the opcode that jammed the cpu
+        self.halted = true;
+        self.pc = self.pc.wrapping_sub(1);
+    }
+
+    fn lax(&mut self, val: u8)
+    {
+        self.a = val;
+        self.x = val;
+        self.update_zero_and_negative_flags(val);
+    }
+
+    fn anc(&mut self, val: u8)
+    {
+        self.and(val);
+        if self.get_negative_flag() != 0
+        {
+            self.set_carry_flag();
+        }
+        else
+        {
+            self.reset_carry_flag();
+        }
+    }
+
+    fn alr(&mut self, val: u8)
+    {
+        self.and(val);
+        let value = self.a;
+        self.a = self.shift_right(value);
+    }
+
+    fn arr(&mut self, val: u8)
+    {
+        self.and(val);
+        let value = self.a;
+        self.a = self.rotate_right(value);
+
+        // C comes from bit 6 of the result and V from bit 6 XOR bit 5
+        let result = self.a;
+        if result & 0b01000000 != 0
+        {
+            self.set_carry_flag();
+        }
+        else
+        {
+            self.reset_carry_flag();
+        }
+        if ((result >> 6) ^ (result >> 5)) & 0b00000001 != 0
+        {
+            self.set_overflow_flag();
+        }
+        else
+        {
+            self.reset_overflow_flag();
+        }
+    }
+
+    fn axs(&mut self, val: u8)
+    {
+        let value = self.a & self.x;
+        self.compare(value, val);
+        self.x = value.wrapping_sub(val);
+    }
+
+    fn xaa(&mut self, val: u8)
+    {
+        self.a = (self.a | UNSTABLE_MAGIC) & self.x & val;
+        let value = self.a;
+        self.update_zero_and_negative_flags(value);
+    }
+
+    fn lxa(&mut self, val: u8)
+    {
+        let value = (self.a | UNSTABLE_MAGIC) & val;
+        self.lax(value);
+    }
+
+    fn las(&mut self, val: u8)
+    {
+        let value = val & self.s;
+        self.s = value;
+        self.lax(value);
     }
 }
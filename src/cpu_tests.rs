@@ -0,0 +1,592 @@
+use std::env;
+use std::fs;
+
+use serde_json::Value;
+
+use crate::bus::*;
+use crate::cpu::*;
+use crate::opcode::*;
+
+// Register and memory state on either side of a single instruction.
+// Only the listed memory locations are seeded or checked.
+#[derive(Clone, Copy)]
+struct State
+{
+    pc: u16,
+    a: u8,
+    x: u8,
+    y: u8,
+    s: u8,
+    p: u8,
+    memory: &'static [(u16, u8)],
+}
+
+struct Case
+{
+    name: &'static str,
+    program: &'static [u8], // loaded at initial.pc
+    initial: State,
+    expected: State,
+    cycles: u16,
+}
+
+const BASE: State = State { pc: 0x0600, a: 0x00, x: 0x00, y: 0x00, s: 0xFD, p: 0x24, memory: &[] };
+
+static CASES: &[Case] = &[
+    Case { name: "LDA immediate sets N", program: &[0xA9, 0x80],
+           initial: BASE,
+           expected: State { pc: 0x0602, a: 0x80, p: 0xA4, ..BASE }, cycles: 2 },
+    Case { name: "LDA zero page sets Z", program: &[0xA5, 0x10],
+           initial: State { a: 0x33, memory: &[(0x0010, 0x00)], ..BASE },
+           expected: State { pc: 0x0602, a: 0x00, p: 0x26, ..BASE }, cycles: 3 },
+    Case { name: "ADC signed overflow", program: &[0x69, 0x50],
+           initial: State { a: 0x50, ..BASE },
+           expected: State { pc: 0x0602, a: 0xA0, p: 0xE4, ..BASE }, cycles: 2 },
+    Case { name: "ADC carry in and out", program: &[0x69, 0x01],
+           initial: State { a: 0xFF, p: 0x25, ..BASE },
+           expected: State { pc: 0x0602, a: 0x01, p: 0x25, ..BASE }, cycles: 2 },
+    Case { name: "SBC borrow", program: &[0xE9, 0x01],
+           initial: State { a: 0x00, p: 0x25, ..BASE },
+           expected: State { pc: 0x0602, a: 0xFF, p: 0xA4, ..BASE }, cycles: 2 },
+    Case { name: "CMP takes N from the difference", program: &[0xC9, 0xFF],
+           initial: State { a: 0x01, ..BASE },
+           expected: State { pc: 0x0602, a: 0x01, p: 0x24, ..BASE }, cycles: 2 },
+    Case { name: "CPX equal", program: &[0xE0, 0x10],
+           initial: State { x: 0x10, ..BASE },
+           expected: State { pc: 0x0602, x: 0x10, p: 0x27, ..BASE }, cycles: 2 },
+    Case { name: "LDA absolute,X page cross", program: &[0xBD, 0xF0, 0x02],
+           initial: State { x: 0x20, memory: &[(0x0310, 0x42)], ..BASE },
+           expected: State { pc: 0x0603, a: 0x42, x: 0x20, ..BASE }, cycles: 5 },
+    Case { name: "STA absolute,X has no page cross penalty", program: &[0x9D, 0xF0, 0x02],
+           initial: State { a: 0x42, x: 0x20, ..BASE },
+           expected: State { pc: 0x0603, a: 0x42, x: 0x20, memory: &[(0x0310, 0x42)], ..BASE }, cycles: 5 },
+    Case { name: "LDA (zp,X) pointer wraps in zero page", program: &[0xA1, 0xF0],
+           initial: State { x: 0x0F, memory: &[(0x00FF, 0x34), (0x0000, 0x12), (0x1234, 0x99)], ..BASE },
+           expected: State { pc: 0x0602, a: 0x99, x: 0x0F, p: 0xA4, ..BASE }, cycles: 6 },
+    Case { name: "LDA (zp),Y page cross", program: &[0xB1, 0x10],
+           initial: State { y: 0xFF, memory: &[(0x0010, 0x01), (0x0011, 0x03), (0x0400, 0x07)], ..BASE },
+           expected: State { pc: 0x0602, a: 0x07, y: 0xFF, ..BASE }, cycles: 6 },
+    Case { name: "STA (zp),Y", program: &[0x91, 0x10],
+           initial: State { a: 0x5A, y: 0x04, memory: &[(0x0010, 0x00), (0x0011, 0x03)], ..BASE },
+           expected: State { pc: 0x0602, a: 0x5A, y: 0x04, memory: &[(0x0304, 0x5A)], ..BASE }, cycles: 6 },
+    Case { name: "BNE taken onto the previous page", program: &[0xD0, 0xFC],
+           initial: BASE,
+           expected: State { pc: 0x05FE, ..BASE }, cycles: 4 },
+    Case { name: "BNE taken on the same page", program: &[0xD0, 0x10],
+           initial: BASE,
+           expected: State { pc: 0x0612, ..BASE }, cycles: 3 },
+    Case { name: "BEQ not taken", program: &[0xF0, 0x10],
+           initial: BASE,
+           expected: State { pc: 0x0602, ..BASE }, cycles: 2 },
+    Case { name: "JSR pushes the address of its last byte", program: &[0x20, 0x00, 0x07],
+           initial: BASE,
+           expected: State { pc: 0x0700, s: 0xFB, memory: &[(0x01FD, 0x06), (0x01FC, 0x02)], ..BASE }, cycles: 6 },
+    Case { name: "RTS", program: &[0x60],
+           initial: State { s: 0xFB, memory: &[(0x01FC, 0x02), (0x01FD, 0x06)], ..BASE },
+           expected: State { pc: 0x0603, ..BASE }, cycles: 6 },
+    Case { name: "PHA wraps within page one", program: &[0x48],
+           initial: State { a: 0x55, s: 0x00, ..BASE },
+           expected: State { pc: 0x0601, a: 0x55, s: 0xFF, memory: &[(0x0100, 0x55)], ..BASE }, cycles: 3 },
+    Case { name: "PHP pushes B and bit 5", program: &[0x08],
+           initial: BASE,
+           expected: State { pc: 0x0601, s: 0xFC, memory: &[(0x01FD, 0x34)], ..BASE }, cycles: 3 },
+    Case { name: "PLP ignores B", program: &[0x28],
+           initial: State { s: 0xFC, memory: &[(0x01FD, 0xFF)], ..BASE },
+           expected: State { pc: 0x0601, p: 0xEF, ..BASE }, cycles: 4 },
+    Case { name: "JMP indirect does not cross pages", program: &[0x6C, 0xFF, 0x02],
+           initial: State { memory: &[(0x02FF, 0x00), (0x0200, 0x08), (0x0300, 0x09)], ..BASE },
+           expected: State { pc: 0x0800, ..BASE }, cycles: 5 },
+    Case { name: "BRK jumps through the IRQ vector", program: &[0x00],
+           initial: State { p: 0x20, memory: &[(0xFFFE, 0x00), (0xFFFF, 0x80)], ..BASE },
+           expected: State { pc: 0x8000, s: 0xFA, p: 0x24,
+                             memory: &[(0x01FD, 0x06), (0x01FC, 0x02), (0x01FB, 0x30)], ..BASE }, cycles: 7 },
+    Case { name: "RTI restores flags and pc", program: &[0x40],
+           initial: State { s: 0xFA, memory: &[(0x01FB, 0xC3), (0x01FC, 0x34), (0x01FD, 0x12)], ..BASE },
+           expected: State { pc: 0x1234, p: 0xE3, ..BASE }, cycles: 6 },
+    Case { name: "ASL zero page", program: &[0x06, 0x10],
+           initial: State { memory: &[(0x0010, 0x81)], ..BASE },
+           expected: State { pc: 0x0602, p: 0x25, memory: &[(0x0010, 0x02)], ..BASE }, cycles: 5 },
+    Case { name: "ROR accumulator through carry", program: &[0x6A],
+           initial: State { a: 0x01, p: 0x25, ..BASE },
+           expected: State { pc: 0x0601, a: 0x80, p: 0xA5, ..BASE }, cycles: 2 },
+    Case { name: "BIT copies N and V", program: &[0x24, 0x10],
+           initial: State { memory: &[(0x0010, 0xC0)], ..BASE },
+           expected: State { pc: 0x0602, p: 0xE6, ..BASE }, cycles: 3 },
+    Case { name: "INX wraps", program: &[0xE8],
+           initial: State { x: 0xFF, ..BASE },
+           expected: State { pc: 0x0601, x: 0x00, p: 0x26, ..BASE }, cycles: 2 },
+    Case { name: "DEC zero page,X wraps the index", program: &[0xD6, 0xF0],
+           initial: State { x: 0x20, memory: &[(0x0010, 0x01)], ..BASE },
+           expected: State { pc: 0x0602, x: 0x20, p: 0x26, memory: &[(0x0010, 0x00)], ..BASE }, cycles: 6 },
+    Case { name: "LAX zero page", program: &[0xA7, 0x10],
+           initial: State { memory: &[(0x0010, 0x80)], ..BASE },
+           expected: State { pc: 0x0602, a: 0x80, x: 0x80, p: 0xA4, ..BASE }, cycles: 3 },
+    Case { name: "DCP zero page", program: &[0xC7, 0x10],
+           initial: State { a: 0x40, memory: &[(0x0010, 0x41)], ..BASE },
+           expected: State { pc: 0x0602, a: 0x40, p: 0x27, memory: &[(0x0010, 0x40)], ..BASE }, cycles: 5 },
+    Case { name: "NOP absolute,X pays the page cross", program: &[0x1C, 0xF0, 0x02],
+           initial: State { x: 0x20, ..BASE },
+           expected: State { pc: 0x0603, x: 0x20, ..BASE }, cycles: 5 },
+];
+
+fn load_state(cpu: &mut CPU<FlatBus>, state: &State)
+{
+    cpu.set_pc(state.pc);
+    cpu.set_a(state.a);
+    cpu.set_x(state.x);
+    cpu.set_y(state.y);
+    cpu.set_s(state.s);
+    cpu.set_p(state.p);
+    for &(address, val) in state.memory
+    {
+        cpu.get_bus_mut().write(address, val);
+    }
+}
+
+fn run_case(case: &Case)
+{
+    let mut cpu = init_cpu(init_flat_bus());
+    for (i, byte) in case.program.iter().enumerate()
+    {
+        cpu.get_bus_mut().write(case.initial.pc + i as u16, *byte);
+    }
+    load_state(&mut cpu, &case.initial);
+
+    let cycles = cpu.step();
+
+    let expected = &case.expected;
+    assert_eq!(cpu.get_pc(), expected.pc, "{}: pc", case.name);
+    assert_eq!(cpu.get_a(), expected.a, "{}: a", case.name);
+    assert_eq!(cpu.get_x(), expected.x, "{}: x", case.name);
+    assert_eq!(cpu.get_y(), expected.y, "{}: y", case.name);
+    assert_eq!(cpu.get_s(), expected.s, "{}: s", case.name);
+    assert_eq!(cpu.get_p(), expected.p, "{}: p", case.name);
+    for &(address, val) in expected.memory
+    {
+        assert_eq!(cpu.get_bus_mut().peek(address), val, "{}: memory at {:#06X}", case.name, address);
+    }
+    assert_eq!(cycles, case.cycles, "{}: cycles", case.name);
+}
+
+#[test]
+fn single_instruction_cases()
+{
+    for case in CASES
+    {
+        run_case(case);
+    }
+}
+
+#[test]
+fn kil_halts_until_reset()
+{
+    let mut cpu = init_cpu(init_flat_bus());
+    cpu.get_bus_mut().write(0x0600, 0x02);
+    cpu.get_bus_mut().write(0xFFFC, 0x00);
+    cpu.get_bus_mut().write(0xFFFD, 0x06);
+    cpu.reset();
+
+    cpu.step();
+    assert!(cpu.is_halted());
+    assert_eq!(cpu.get_pc(), 0x0600);
+    assert_eq!(cpu.step(), 0);
+
+    cpu.reset();
+    assert!(!cpu.is_halted());
+}
+
+// FlatBus that adds up the cycles the CPU ticks it for
+struct TickCountingBus
+{
+    bus: FlatBus,
+    ticked: u64,
+}
+
+impl Bus for TickCountingBus
+{
+    fn read(&mut self, address: u16) -> u8
+    {
+        return self.bus.read(address);
+    }
+
+    fn write(&mut self, address: u16, val: u8)
+    {
+        self.bus.write(address, val);
+    }
+
+    fn tick(&mut self, cycles: u16)
+    {
+        self.ticked += cycles as u64;
+    }
+}
+
+#[test]
+fn reset_ticks_the_bus()
+{
+    let mut cpu = init_cpu(TickCountingBus { bus: init_flat_bus(), ticked: 0 });
+    cpu.get_bus_mut().write(0x0600, 0xEA);
+    cpu.get_bus_mut().write(0xFFFC, 0x00);
+    cpu.get_bus_mut().write(0xFFFD, 0x06);
+    cpu.reset();
+    assert_eq!(cpu.get_bus().ticked, 7);
+
+    cpu.step();
+    assert_eq!(cpu.get_bus().ticked, cpu.get_cycles());
+}
+
+#[test]
+fn store_bus_activity()
+{
+    let mut cpu = init_cpu(init_flat_bus());
+    load_state(&mut cpu, &State { a: 0x42, memory: &[(0x0600, 0x8D), (0x0601, 0x34), (0x0602, 0x12)], ..BASE });
+    cpu.get_bus_mut().set_log_accesses(true);
+
+    cpu.step();
+    assert_eq!(*cpu.get_bus().get_accesses(), vec![
+        BusAccess::Read(0x0600, 0x8D),
+        BusAccess::Read(0x0601, 0x34),
+        BusAccess::Read(0x0602, 0x12),
+        BusAccess::Write(0x1234, 0x42),
+    ]);
+}
+
+#[test]
+fn nmos_decimal_mode()
+{
+    let mut cpu = init_cpu(init_flat_bus());
+    cpu.set_variant(Variant::Nmos6502);
+    load_state(&mut cpu, &State { a: 0x58, p: 0x2D, memory: &[(0x0600, 0x69), (0x0601, 0x46)], ..BASE });
+    cpu.step();
+    assert_eq!(cpu.get_a(), 0x05);
+    assert_eq!(cpu.get_carry_flag(), 0x01);
+
+    // the 2A03 ignores the decimal flag
+    cpu.set_variant(Variant::Ricoh2A03);
+    load_state(&mut cpu, &State { a: 0x58, p: 0x2D, memory: &[(0x0600, 0x69), (0x0601, 0x46)], ..BASE });
+    cpu.step();
+    assert_eq!(cpu.get_a(), 0x9F);
+}
+
+// program at 0x0600, NMI handler at 0x9000 and IRQ handler at 0x8000, both NOP slides
+fn interrupt_cpu(program: &[u8], p: u8) -> CPU<FlatBus>
+{
+    let mut cpu = init_cpu(init_flat_bus());
+    load_state(&mut cpu, &State { p, memory: &[(0xFFFA, 0x00), (0xFFFB, 0x90), (0xFFFE, 0x00), (0xFFFF, 0x80)], ..BASE });
+    for i in 0..0x10
+    {
+        cpu.get_bus_mut().write(0x8000 + i, 0xEA);
+        cpu.get_bus_mut().write(0x9000 + i, 0xEA);
+    }
+    for (i, byte) in program.iter().enumerate()
+    {
+        cpu.get_bus_mut().write(0x0600 + i as u16, *byte);
+    }
+    return cpu;
+}
+
+#[test]
+fn irq_is_masked_by_the_interrupt_flag()
+{
+    let mut cpu = interrupt_cpu(&[0xEA, 0xEA], 0x24);
+    cpu.set_irq_line(true);
+    cpu.step();
+    cpu.step();
+    assert_eq!(cpu.get_pc(), 0x0602);
+}
+
+#[test]
+fn cli_takes_effect_one_instruction_late()
+{
+    let mut cpu = interrupt_cpu(&[0x58, 0xEA, 0xEA], 0x24);
+    cpu.set_irq_line(true);
+    cpu.step(); // CLI
+    cpu.step(); // the NOP still runs
+    assert_eq!(cpu.get_pc(), 0x0602);
+
+    assert_eq!(cpu.step(), 7);
+    assert_eq!(cpu.get_pc(), 0x8000);
+    assert_eq!(cpu.get_s(), 0xFA);
+    let bus = cpu.get_bus_mut();
+    assert_eq!((bus.peek(0x01FD), bus.peek(0x01FC), bus.peek(0x01FB)), (0x06, 0x02, 0x20));
+}
+
+#[test]
+fn sei_lets_a_pending_irq_through()
+{
+    let mut cpu = interrupt_cpu(&[0x78, 0xEA], 0x20);
+    cpu.set_irq_line(true);
+    // the line was already held, so the poll before the SEI fires first
+    cpu.step();
+    assert_eq!(cpu.get_pc(), 0x8000);
+
+    let mut cpu = interrupt_cpu(&[0x78, 0xEA], 0x20);
+    cpu.step(); // SEI
+    cpu.set_irq_line(true);
+    assert_eq!(cpu.step(), 7);
+    assert_eq!(cpu.get_pc(), 0x8000);
+    assert_eq!(cpu.get_bus_mut().peek(0x01FB), 0x24);
+}
+
+#[test]
+fn nmi_is_edge_triggered()
+{
+    let mut cpu = interrupt_cpu(&[0xEA], 0x24);
+    cpu.set_nmi_line(true);
+    assert_eq!(cpu.step(), 7);
+    assert_eq!(cpu.get_pc(), 0x9000);
+
+    // holding the line does not raise another one
+    cpu.step();
+    cpu.step();
+    assert_eq!(cpu.get_pc(), 0x9002);
+
+    cpu.set_nmi_line(false);
+    cpu.set_nmi_line(true);
+    cpu.step();
+    assert_eq!(cpu.get_pc(), 0x9000);
+    assert_eq!(cpu.get_s(), 0xF7);
+}
+
+#[test]
+fn taken_branch_delays_interrupts()
+{
+    // BNE +0 taken on the same page, the IRQ waits for the NOP
+    let mut cpu = interrupt_cpu(&[0xD0, 0x00, 0xEA], 0x20);
+    cpu.step();
+    cpu.set_irq_line(true);
+    cpu.step();
+    assert_eq!(cpu.get_pc(), 0x0603);
+    cpu.step();
+    assert_eq!(cpu.get_pc(), 0x8000);
+
+    // an IRQ already held when the branch starts is seen by its early poll
+    let mut cpu = interrupt_cpu(&[0x58, 0xD0, 0x00, 0xEA], 0x24);
+    cpu.set_irq_line(true);
+    cpu.step();
+    assert_eq!(cpu.get_pc(), 0x0601);
+    cpu.step();
+    assert_eq!(cpu.get_pc(), 0x0603);
+    cpu.step();
+    assert_eq!(cpu.get_pc(), 0x8000);
+
+    // BEQ not taken polls as usual
+    let mut cpu = interrupt_cpu(&[0xF0, 0x00, 0xEA], 0x20);
+    cpu.step();
+    cpu.set_irq_line(true);
+    cpu.step();
+    assert_eq!(cpu.get_pc(), 0x8000);
+}
+
+
+////////////////////////////////////////////////////
+////////////////////////////////////////////////////
+// JSON TEST VECTORS //
+////////////////////////////////////////////////////
+////////////////////////////////////////////////////
+
+// Runs the per-opcode JSON vectors (one file per opcode, e.g. "a9.json",
+// each an array of cases with "initial", "final" and "cycles") found in
+// the directory named by RUSTNES_CPU_TESTS. The NES set is run against the
+// 2A03, set RUSTNES_CPU_VARIANT=nmos for the generic 6502 set.
+//
+// The vectors are not bundled with the crate, so the test is ignored unless
+// asked for: RUSTNES_CPU_TESTS=dir cargo test json_test_vectors -- --ignored
+//
+// The bus activity is compared cycle by cycle: address, value and direction
+// of every access. The core makes no dummy accesses, so the opcodes below
+// are known gaps and only have their cycle count checked.
+
+// Opcodes whose hardware bus activity always has accesses the core skips:
+// the dummy read after implied and accumulator instructions (stack
+// instructions and BRK included), the dummy read of the base address in
+// zero page indexed and (zp,X) addressing, the dummy write of read-modify-write
+// instructions, the dummy read of indexed stores, and JSR, which reads its
+// high address byte before the pushes instead of after them.
+static BUS_ACTIVITY_GAPS: [u8; 140] =
+[
+    0x00, 0x01, 0x03, 0x06, 0x07, 0x08, 0x0A, 0x0E, 0x0F, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x1A,
+    0x1B, 0x1E, 0x1F, 0x20, 0x21, 0x23, 0x26, 0x27, 0x28, 0x2A, 0x2E, 0x2F, 0x33, 0x34, 0x35, 0x36,
+    0x37, 0x38, 0x3A, 0x3B, 0x3E, 0x3F, 0x40, 0x41, 0x43, 0x46, 0x47, 0x48, 0x4A, 0x4E, 0x4F, 0x53,
+    0x54, 0x55, 0x56, 0x57, 0x58, 0x5A, 0x5B, 0x5E, 0x5F, 0x60, 0x61, 0x63, 0x66, 0x67, 0x68, 0x6A,
+    0x6E, 0x6F, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x7A, 0x7B, 0x7E, 0x7F, 0x81, 0x83, 0x88, 0x8A,
+    0x91, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9A, 0x9B, 0x9C, 0x9D, 0x9E, 0x9F, 0xA1, 0xA3,
+    0xA8, 0xAA, 0xB4, 0xB5, 0xB6, 0xB7, 0xB8, 0xBA, 0xC1, 0xC3, 0xC6, 0xC7, 0xC8, 0xCA, 0xCE, 0xCF,
+    0xD3, 0xD4, 0xD5, 0xD6, 0xD7, 0xD8, 0xDA, 0xDB, 0xDE, 0xDF, 0xE1, 0xE3, 0xE6, 0xE7, 0xE8, 0xEA,
+    0xEE, 0xEF, 0xF3, 0xF4, 0xF5, 0xF6, 0xF7, 0xF8, 0xFA, 0xFB, 0xFE, 0xFF,
+];
+
+// Opcodes that only have a dummy read when they take their penalty cycle:
+// indexed reads crossing a page and taken branches.
+static PENALTY_BUS_ACTIVITY_GAPS: [u8; 40] =
+[
+    0x10, 0x11, 0x19, 0x1C, 0x1D, 0x30, 0x31, 0x39, 0x3C, 0x3D, 0x50, 0x51, 0x59, 0x5C, 0x5D, 0x70,
+    0x71, 0x79, 0x7C, 0x7D, 0x90, 0xB0, 0xB1, 0xB3, 0xB9, 0xBB, 0xBC, 0xBD, 0xBE, 0xBF, 0xD0, 0xD1,
+    0xD9, 0xDC, 0xDD, 0xF0, 0xF1, 0xF9, 0xFC, 0xFD,
+];
+
+fn field(state: &Value, name: &str) -> u64
+{
+    return state[name].as_u64().unwrap_or_else(|| panic!("missing field {}", name));
+}
+
+fn expected_access(cycle: &Value) -> BusAccess
+{
+    let address = cycle[0].as_u64().unwrap() as u16;
+    let val = cycle[1].as_u64().unwrap() as u8;
+    if cycle[2].as_str() == Some("write")
+    {
+        return BusAccess::Write(address, val);
+    }
+    return BusAccess::Read(address, val);
+}
+
+fn check_bus_activity(accesses: &[BusAccess], cycles: &[Value]) -> Result<(), String>
+{
+    for (i, cycle) in cycles.iter().enumerate()
+    {
+        let expected = expected_access(cycle);
+        match accesses.get(i)
+        {
+            Some(access) if *access == expected => (),
+            Some(access) => return Err(format!("cycle {} {:?} != {:?}", i, access, expected)),
+            None => return Err(format!("cycle {} missing {:?}", i, expected)),
+        }
+    }
+    if accesses.len() > cycles.len()
+    {
+        return Err(format!("extra access {:?}", accesses[cycles.len()]));
+    }
+    return Ok(());
+}
+
+fn run_vector(code: u8, vector: &Value, variant: Variant) -> Result<(), String>
+{
+    let initial = &vector["initial"];
+    let expected = &vector["final"];
+
+    let mut cpu = init_cpu(init_flat_bus());
+    cpu.set_variant(variant);
+    cpu.set_pc(field(initial, "pc") as u16);
+    cpu.set_a(field(initial, "a") as u8);
+    cpu.set_x(field(initial, "x") as u8);
+    cpu.set_y(field(initial, "y") as u8);
+    cpu.set_s(field(initial, "s") as u8);
+    cpu.set_p(field(initial, "p") as u8);
+    for cell in initial["ram"].as_array().unwrap_or(&Vec::new())
+    {
+        cpu.get_bus_mut().write(cell[0].as_u64().unwrap() as u16, cell[1].as_u64().unwrap() as u8);
+    }
+
+    cpu.get_bus_mut().set_log_accesses(true);
+    let cycles = cpu.step();
+
+    let mut errors = Vec::new();
+    let registers = [("pc", cpu.get_pc() as u64), ("a", cpu.get_a() as u64), ("x", cpu.get_x() as u64),
+                     ("y", cpu.get_y() as u64), ("s", cpu.get_s() as u64)];
+    for &(name, actual) in registers.iter()
+    {
+        if actual != field(expected, name)
+        {
+            errors.push(format!("{} {:#X} != {:#X}", name, actual, field(expected, name)));
+        }
+    }
+
+    // B and bit 5 only exist on the stack
+    let p = cpu.get_p() | 0b00110000;
+    let expected_p = field(expected, "p") as u8 | 0b00110000;
+    if p != expected_p
+    {
+        errors.push(format!("p {:#04X} != {:#04X}", p, expected_p));
+    }
+
+    for cell in expected["ram"].as_array().unwrap_or(&Vec::new())
+    {
+        let address = cell[0].as_u64().unwrap() as u16;
+        let val = cell[1].as_u64().unwrap() as u8;
+        let actual = cpu.get_bus_mut().peek(address);
+        if actual != val
+        {
+            errors.push(format!("ram[{:#06X}] {:#04X} != {:#04X}", address, actual, val));
+        }
+    }
+
+    let no_cycles = Vec::new();
+    let expected_cycles = vector["cycles"].as_array().unwrap_or(&no_cycles);
+    if cycles as usize != expected_cycles.len()
+    {
+        errors.push(format!("cycles {} != {}", cycles, expected_cycles.len()));
+    }
+    let known_gap = BUS_ACTIVITY_GAPS.contains(&code)
+        || (PENALTY_BUS_ACTIVITY_GAPS.contains(&code) && expected_cycles.len() > decode(code).cycles as usize);
+    if !known_gap
+    {
+        if let Err(error) = check_bus_activity(cpu.get_bus().get_accesses(), expected_cycles)
+        {
+            errors.push(error);
+        }
+    }
+
+    if errors.is_empty()
+    {
+        return Ok(());
+    }
+    return Err(errors.join(", "));
+}
+
+#[test]
+#[ignore = "needs RUSTNES_CPU_TESTS set to the JSON vector directory"]
+fn json_test_vectors()
+{
+    let dir = env::var("RUSTNES_CPU_TESTS").expect("Set RUSTNES_CPU_TESTS to the JSON vector directory!");
+    let variant = match env::var("RUSTNES_CPU_VARIANT")
+    {
+        Ok(ref name) if name == "nmos" => Variant::Nmos6502,
+        _ => Variant::Ricoh2A03,
+    };
+
+    let mut paths: Vec<_> = fs::read_dir(&dir).expect("Could not read the test vector directory!")
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().map(|ext| ext == "json").unwrap_or(false))
+        .collect();
+    paths.sort();
+
+    let mut failures = Vec::new();
+    for path in paths
+    {
+        let stem = path.file_stem().unwrap().to_string_lossy().to_string();
+        let code = match u8::from_str_radix(&stem, 16)
+        {
+            Ok(code) => code,
+            Err(_) => continue,
+        };
+        // a jammed cpu has no defined end state to compare against
+        if decode(code).mnemonic == Mnemonic::Kil
+        {
+            continue;
+        }
+
+        let text = fs::read_to_string(&path).expect("Could not read a test vector file!");
+        let vectors: Value = serde_json::from_str(&text).expect("Test vector file is not valid JSON!");
+        let vectors = vectors.as_array().expect("Test vector file should hold an array!");
+
+        let mut failed = 0;
+        let mut first_failure = String::new();
+        for vector in vectors
+        {
+            if let Err(error) = run_vector(code, vector, variant)
+            {
+                if failed == 0
+                {
+                    first_failure = format!("{}: {}", vector["name"], error);
+                }
+                failed += 1;
+            }
+        }
+        if failed > 0
+        {
+            failures.push(format!("{:02X}: {}/{} failed, first {}", code, failed, vectors.len(), first_failure));
+        }
+    }
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
@@ -1,18 +1,113 @@
+use std::error::Error;
+use std::fmt;
 use std::fs::File;
+use std::io;
 use std::io::Read;
 
+use crate::bus::*;
+use crate::cartridge::*;
+use crate::cpu::*;
+use crate::header::*;
+use crate::mapper::is_supported_mapper;
 
-pub fn open_file(path: String) -> File
+static TRAINER_SIZE: usize = 0x200;
+// 8KB INST-ROM followed by 16 bytes of PROM data and 16 of PROM CounterOut
+static PLAYCHOICE_TRAILER_SIZE: usize = 0x2000 + 0x20;
+
+// everything that can make an iNES file unusable
+#[derive(Debug)]
+pub enum RomError
+{
+    Io(io::Error),
+    TooShort(usize), // file length, less than a header
+    BadMagic([u8; 4]),
+    SizeMismatch { expected: usize, actual: usize }, // header + trainer + PRG + CHR vs the file length
+    UnsupportedMapper(u16),
+    TrainerTruncated,
+}
+
+impl fmt::Display for RomError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        return match *self
+        {
+            RomError::Io(ref error) => write!(f, "could not read the rom: {}", error),
+            RomError::TooShort(length) => write!(f, "file is {} bytes, too short for an iNES header", length),
+            RomError::BadMagic(magic) => write!(f, "not an iNES file, magic is {:02X?}", magic),
+            RomError::SizeMismatch { expected, actual } =>
+                write!(f, "header promises {} bytes of trainer and ROM but the file holds {}", expected, actual),
+            RomError::UnsupportedMapper(mapper) => write!(f, "mapper {} is not supported", mapper),
+            RomError::TrainerTruncated => write!(f, "file ends inside the trainer"),
+        };
+    }
+}
+
+impl Error for RomError
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)>
+    {
+        return match *self
+        {
+            RomError::Io(ref error) => Some(error),
+            _ => None,
+        };
+    }
+}
+
+impl From<io::Error> for RomError
 {
-        let _f = File::open(path).expect("Something went wrong opening the file!");
-        return _f;
+    fn from(error: io::Error) -> RomError
+    {
+        return RomError::Io(error);
     }
+}
 
-pub fn store_file(mut f: File) -> Vec<u8>
+// an iNES file split into its parts
+#[derive(Debug, Clone)]
+pub struct Rom
+{
+    header: Header,
+    trainer: Option<Vec<u8>>,
+    prg_rom: Vec<u8>,
+    chr_rom: Vec<u8>,
+}
+
+impl Rom
+{
+    pub fn get_header(&self) -> &Header
+    {
+        return &self.header;
+    }
+
+    pub fn get_trainer(&self) -> Option<&Vec<u8>>
+    {
+        return self.trainer.as_ref();
+    }
+
+    pub fn get_prg_rom(&self) -> &Vec<u8>
+    {
+        return &self.prg_rom;
+    }
+
+    pub fn get_chr_rom(&self) -> &Vec<u8>
+    {
+        return &self.chr_rom;
+    }
+}
+
+
+pub fn open_file(path: String) -> io::Result<File>
+{
+        let _f = File::open(path)?;
+        return Ok(_f);
+    }
+
+pub fn store_file(mut f: File) -> io::Result<Vec<u8>>
 {
         let mut _buffer = Vec::new();
-        f.read_to_end(&mut _buffer).expect("Something went wrong reading the file!");
-        return _buffer;
+        f.read_to_end(&mut _buffer)?;
+        return Ok(_buffer);
     }
 
 pub fn print_rom(mut _rom: Vec<u8>)
@@ -22,3 +117,68 @@ pub fn print_rom(mut _rom: Vec<u8>)
             println!("{:#04X}: {:#04X}", i, element);
         }
     }
+
+// Check an in-memory iNES image and split it into header, trainer, PRG and CHR.
+// The file has to end right after CHR-ROM unless the header announces a
+// trailer: the PlayChoice INST-ROM and PROM, or NES 2.0 misc ROMs. Trailers
+// are not kept.
+pub fn parse_rom(buffer: &[u8]) -> Result<Rom, RomError>
+{
+    let h = parse_header(buffer)?;
+
+    let mut offset = HEADER_SIZE;
+    let mut trainer = None;
+    if h.has_trainer()
+    {
+        if buffer.len() < offset + TRAINER_SIZE
+        {
+            return Err(RomError::TrainerTruncated);
+        }
+        trainer = Some(buffer[offset..offset + TRAINER_SIZE].to_vec());
+        offset += TRAINER_SIZE;
+    }
+
+    let prg_size = h.get_prg_rom_size();
+    let chr_size = h.get_chr_rom_size();
+    let expected = offset.saturating_add(prg_size).saturating_add(chr_size);
+    let playchoice_trailer = h.is_playchoice() && buffer.len() == expected.saturating_add(PLAYCHOICE_TRAILER_SIZE);
+    // misc ROM sizes are not in the header, they take up the rest of the file
+    let misc_roms = h.get_format() == HeaderFormat::Nes2 && h.get_misc_rom_count() > 0 && buffer.len() > expected;
+    if buffer.len() != expected && !playchoice_trailer && !misc_roms
+    {
+        return Err(RomError::SizeMismatch { expected, actual: buffer.len() });
+    }
+
+    if !is_supported_mapper(h.get_mapper())
+    {
+        return Err(RomError::UnsupportedMapper(h.get_mapper()));
+    }
+
+    let rom = Rom
+    {
+        header: h,
+        trainer,
+        prg_rom: buffer[offset..offset + prg_size].to_vec(),
+        chr_rom: buffer[offset + prg_size..expected].to_vec(),
+    };
+    return Ok(rom);
+}
+
+pub fn load_rom(path: String) -> Result<Rom, RomError>
+{
+    let buffer = store_file(open_file(path)?)?;
+    return parse_rom(&buffer);
+}
+
+// Load an iNES file, plug its cartridge into the bus and bring the cpu out of reset
+pub fn load_cpu(path: String) -> Result<CPU<NesBus>, RomError>
+{
+    let cartridge = init_cartridge(load_rom(path)?)?;
+
+    let mut bus = init_nes_bus();
+    bus.insert_cartridge(cartridge);
+
+    let mut cpu = init_cpu(bus);
+    cpu.reset();
+    return Ok(cpu);
+}
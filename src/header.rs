@@ -1,50 +1,397 @@
-use opcode;
-use opcode::Opcode;
+use crate::file_handling::RomError;
+
+pub static HEADER_SIZE: usize = 0x10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderFormat
+{
+    Archaic, // bytes 7-15 hold garbage, only bytes 4-6 can be trusted
+    INes,
+    Nes2,
+}
+
+// nametable layout wired up by the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mirroring
+{
+    Horizontal,
+    Vertical,
+    FourScreen, // extra VRAM on the cartridge
+    // only selectable by a mapper, every nametable shows the same 1KB
+    SingleScreenLower,
+    SingleScreenUpper,
+}
+
+// CPU/PPU timing, iNES can only tell NTSC from PAL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timing
+{
+    Ntsc, // RP2C02
+    Pal, // RP2C07
+    MultiRegion,
+    Dendy, // UA6538
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleType
+{
+    Nes, // NES or Famicom
+    VsSystem { ppu: u8, hardware: u8 },
+    Playchoice,
+    Extended(u8), // NES 2.0 byte 13, e.g. Famiclone with decimal mode
+}
 
 #[derive(Debug, Clone)]
 pub struct Header
 {
-    byte_0: u8, // 0x0
-    byte_1: u8, // 0x1
-    byte_2: u8, // 0x2
-    byte_3: u8, // 0x3
-    prg_rom_size: u8, // 0x4
-    chr_rom_size: u8, // 0x5
-    flags_6: u8, // 0x6
-    flags_7: u8, // 0x7
-    flags_8: u8, // 0x8
-    flags_9: u8, // 0x9
-    flags_10: u8, // 0xA
-}
-
-pub fn set_header(rom: Vec<Opcode>) -> Header
-{
-    let h = Header
-    {
-        byte_0: opcode::get_opcode_code(rom[0x0].clone()),
-        byte_1: opcode::get_opcode_code(rom[0x1].clone()),
-        byte_2: opcode::get_opcode_code(rom[0x2].clone()),
-        byte_3: opcode::get_opcode_code(rom[0x3].clone()),
-        prg_rom_size: opcode::get_opcode_code(rom[0x4].clone()),
-        chr_rom_size: opcode::get_opcode_code(rom[0x5].clone()),
-        flags_6: opcode::get_opcode_code(rom[0x6].clone()),
-        flags_7: opcode::get_opcode_code(rom[0x7].clone()),
-        flags_8: opcode::get_opcode_code(rom[0x8].clone()),
-        flags_9: opcode::get_opcode_code(rom[0x9].clone()),
-        flags_10: opcode::get_opcode_code(rom[0xA].clone()),
+    format: HeaderFormat,
+    prg_rom_size: usize, // in bytes
+    chr_rom_size: usize, // in bytes, 0 means the board has CHR-RAM
+    mirroring: Mirroring, // 0x6 bits 0 and 3
+    battery: bool, // 0x6 bit 1, something on the board keeps its contents
+    trainer: bool, // 0x6 bit 2
+    mapper: u16, // 0x6 and 0x7 upper nibbles, NES 2.0 adds 0x8 bits 0-3
+    submapper: u8, // NES 2.0 0x8 bits 4-7
+    console_type: ConsoleType, // 0x7 bits 0-1
+    prg_ram_size: usize, // volatile, in bytes
+    prg_nvram_size: usize, // battery backed, in bytes
+    chr_ram_size: usize,
+    chr_nvram_size: usize,
+    timing: Timing,
+    misc_rom_count: u8, // NES 2.0 0xE
+    expansion_device: u8, // NES 2.0 0xF
+}
+
+// NES 2.0 ROM size: a 4 bit MSB nibble over the iNES LSB byte, or when the
+// nibble is 0xF the LSB is EEEEEEMM and the size is 2^E * (MM * 2 + 1) bytes
+fn nes2_rom_size(lsb: u8, msb: u8, unit: usize) -> usize
+{
+    if msb == 0xF
+    {
+        let exponent = (lsb >> 2) as u32;
+        let multiplier = (lsb & 0b11) as usize * 2 + 1;
+        // absurd exponents saturate and fail the file length check instead
+        return 1usize.checked_shl(exponent).and_then(|size| size.checked_mul(multiplier)).unwrap_or(usize::MAX);
+    }
+    return (((msb as usize) << 8) | lsb as usize) * unit;
+}
+
+// NES 2.0 RAM size: 64 << shift bytes, with 0 meaning none
+fn nes2_ram_size(shift: u8) -> usize
+{
+    if shift == 0
+    {
+        return 0;
+    }
+    return 64 << shift;
+}
+
+// Build a header from the first 16 bytes of an iNES or NES 2.0 file
+pub fn parse_header(bytes: &[u8]) -> Result<Header, RomError>
+{
+    if bytes.len() < HEADER_SIZE
+    {
+        return Err(RomError::TooShort(bytes.len()));
+    }
+    if bytes[0..4] != *b"NES\x1A"
+    {
+        return Err(RomError::BadMagic([bytes[0], bytes[1], bytes[2], bytes[3]]));
+    }
+
+    let flags_6 = bytes[0x6];
+    let mut flags_7 = bytes[0x7];
+
+    // NES 2.0 marks itself with 0b10 in flags 7 bits 2-3, otherwise old
+    // dumping tools may have written their name ("DiskDude!") over bytes
+    // 7-15 where a well formed iNES header leaves 12-15 zeroed
+    let format = if (flags_7 & 0b00001100) == 0b00001000
+    {
+        HeaderFormat::Nes2
+    }
+    else if bytes[0xC..0x10].iter().any(|byte| *byte != 0x0)
+    {
+        HeaderFormat::Archaic
+    }
+    else
+    {
+        HeaderFormat::INes
+    };
+    if format == HeaderFormat::Archaic
+    {
+        flags_7 = 0x0;
+    }
+
+    let mirroring = if (flags_6 & 0b00001000) != 0
+    {
+        Mirroring::FourScreen
+    }
+    else if (flags_6 & 0b00000001) != 0
+    {
+        Mirroring::Vertical
+    }
+    else
+    {
+        Mirroring::Horizontal
+    };
+
+    let mut h = Header
+    {
+        format,
+        prg_rom_size: bytes[0x4] as usize * 0x4000,
+        chr_rom_size: bytes[0x5] as usize * 0x2000,
+        mirroring,
+        battery: (flags_6 & 0b00000010) != 0,
+        trainer: (flags_6 & 0b00000100) != 0,
+        mapper: ((flags_6 >> 4) | (flags_7 & 0xF0)) as u16,
+        submapper: 0,
+        console_type: match flags_7 & 0b11
+        {
+            1 => ConsoleType::VsSystem { ppu: 0, hardware: 0 },
+            2 => ConsoleType::Playchoice,
+            _ => ConsoleType::Nes,
+        },
+        prg_ram_size: 0,
+        prg_nvram_size: 0,
+        chr_ram_size: 0,
+        chr_nvram_size: 0,
+        timing: Timing::Ntsc,
+        misc_rom_count: 0,
+        expansion_device: 0,
     };
-    return h;
+
+    match format
+    {
+        HeaderFormat::Nes2 =>
+        {
+            h.mapper |= ((bytes[0x8] & 0x0F) as u16) << 8;
+            h.submapper = bytes[0x8] >> 4;
+            h.prg_rom_size = nes2_rom_size(bytes[0x4], bytes[0x9] & 0x0F, 0x4000);
+            h.chr_rom_size = nes2_rom_size(bytes[0x5], bytes[0x9] >> 4, 0x2000);
+            h.prg_ram_size = nes2_ram_size(bytes[0xA] & 0x0F);
+            h.prg_nvram_size = nes2_ram_size(bytes[0xA] >> 4);
+            h.chr_ram_size = nes2_ram_size(bytes[0xB] & 0x0F);
+            h.chr_nvram_size = nes2_ram_size(bytes[0xB] >> 4);
+            h.timing = match bytes[0xC] & 0b11
+            {
+                0 => Timing::Ntsc,
+                1 => Timing::Pal,
+                2 => Timing::MultiRegion,
+                _ => Timing::Dendy,
+            };
+            h.console_type = match flags_7 & 0b11
+            {
+                1 => ConsoleType::VsSystem { ppu: bytes[0xD] & 0x0F, hardware: bytes[0xD] >> 4 },
+                2 => ConsoleType::Playchoice,
+                3 => ConsoleType::Extended(bytes[0xD] & 0x0F),
+                _ => ConsoleType::Nes,
+            };
+            h.misc_rom_count = bytes[0xE] & 0b11;
+            h.expansion_device = bytes[0xF] & 0b00111111;
+        },
+        HeaderFormat::INes =>
+        {
+            // 0 is taken to mean 8KB for compatibility
+            h.prg_ram_size = bytes[0x8].max(1) as usize * 0x2000;
+            h.timing = if (bytes[0x9] & 0b00000001) != 0 { Timing::Pal } else { Timing::Ntsc };
+        },
+        HeaderFormat::Archaic =>
+        {
+            h.prg_ram_size = 0x2000;
+        },
+    }
+
+    // iNES has no CHR-RAM size, boards without CHR-ROM carry 8KB
+    if format != HeaderFormat::Nes2 && h.chr_rom_size == 0
+    {
+        h.chr_ram_size = 0x2000;
+    }
+    return Ok(h);
+}
+
+impl Header
+{
+    pub fn get_format(&self) -> HeaderFormat
+    {
+        return self.format;
+    }
+
+    // PRG-ROM size in bytes
+    pub fn get_prg_rom_size(&self) -> usize
+    {
+        return self.prg_rom_size;
+    }
+
+    // CHR-ROM size in bytes
+    pub fn get_chr_rom_size(&self) -> usize
+    {
+        return self.chr_rom_size;
+    }
+
+    pub fn get_mirroring(&self) -> Mirroring
+    {
+        return self.mirroring;
+    }
+
+    pub fn has_battery(&self) -> bool
+    {
+        return self.battery;
+    }
+
+    // 512 bytes of trainer sit between the header and PRG-ROM
+    pub fn has_trainer(&self) -> bool
+    {
+        return self.trainer;
+    }
+
+    pub fn get_mapper(&self) -> u16
+    {
+        return self.mapper;
+    }
+
+    pub fn get_submapper(&self) -> u8
+    {
+        return self.submapper;
+    }
+
+    pub fn get_console_type(&self) -> ConsoleType
+    {
+        return self.console_type;
+    }
+
+    pub fn is_vs_unisystem(&self) -> bool
+    {
+        return matches!(self.console_type, ConsoleType::VsSystem { .. });
+    }
+
+    pub fn is_playchoice(&self) -> bool
+    {
+        return self.console_type == ConsoleType::Playchoice;
+    }
+
+    pub fn get_prg_ram_size(&self) -> usize
+    {
+        return self.prg_ram_size;
+    }
+
+    pub fn get_prg_nvram_size(&self) -> usize
+    {
+        return self.prg_nvram_size;
+    }
+
+    pub fn get_chr_ram_size(&self) -> usize
+    {
+        return self.chr_ram_size;
+    }
+
+    pub fn get_chr_nvram_size(&self) -> usize
+    {
+        return self.chr_nvram_size;
+    }
+
+    pub fn get_timing(&self) -> Timing
+    {
+        return self.timing;
+    }
+
+    pub fn get_misc_rom_count(&self) -> u8
+    {
+        return self.misc_rom_count;
+    }
+
+    pub fn get_expansion_device(&self) -> u8
+    {
+        return self.expansion_device;
+    }
 }
 
 pub fn print_header(h: Header)
 {
-    println!("Bytes 0-3:    {}{}{}{}",h.byte_0 as char, h.byte_1 as char,
-                                      h.byte_2 as char, h.byte_3 as char);
-    println!("PRG_ROM_SIZE: {:#04X}", h.prg_rom_size);
-    println!("CHR_ROM_SIZE: {:#04X}", h.chr_rom_size);
-    println!("flags_6:      {:#04X}", h.flags_6);
-    println!("flags_7:      {:#04X}", h.flags_7);
-    println!("flags_8:      {:#04X}", h.flags_8);
-    println!("flags_9:      {:#04X}", h.flags_9);
-    println!("flags_10:     {:#04X}", h.flags_10);
+    print!("{}", format_header(&h));
+}
+
+fn yes_no(flag: bool) -> &'static str
+{
+    return if flag { "yes" } else { "no" };
+}
+
+// sizes in the header are rarely under a kilobyte but NES 2.0 allows it
+fn format_size(bytes: usize) -> String
+{
+    if bytes == 0
+    {
+        return String::from("none");
+    }
+    if (bytes & 0x3FF) == 0
+    {
+        return format!("{}KB", bytes / 0x400);
+    }
+    return format!("{} bytes", bytes);
+}
+
+// the first entries of the NES 2.0 default expansion device list
+fn expansion_device_name(device: u8) -> &'static str
+{
+    return match device
+    {
+        0x00 => "unspecified",
+        0x01 => "standard controllers",
+        0x02 => "NES Four Score",
+        0x03 => "Famicom Four Players Adapter",
+        0x04 => "Vs. System",
+        0x05 => "Vs. System, reversed inputs",
+        0x06 => "Vs. Pinball",
+        0x07 => "Vs. Zapper",
+        0x08 => "Zapper",
+        0x09 => "two Zappers",
+        0x0A => "Bandai Hyper Shot",
+        _ => "other",
+    };
+}
+
+pub fn format_header(h: &Header) -> String
+{
+    let mut text = String::new();
+    text += &format!("Format:       {}\n", match h.format
+    {
+        HeaderFormat::Archaic => "archaic iNES",
+        HeaderFormat::INes => "iNES",
+        HeaderFormat::Nes2 => "NES 2.0",
+    });
+    if h.format == HeaderFormat::Nes2
+    {
+        text += &format!("Mapper:       {}.{}\n", h.mapper, h.submapper);
+    }
+    else
+    {
+        text += &format!("Mapper:       {}\n", h.mapper);
+    }
+    text += &format!("PRG-ROM:      {}\n", format_size(h.prg_rom_size));
+    text += &format!("CHR-ROM:      {}\n", format_size(h.chr_rom_size));
+    text += &format!("PRG-RAM:      {}\n", format_size(h.prg_ram_size));
+    text += &format!("PRG-NVRAM:    {}\n", format_size(h.prg_nvram_size));
+    text += &format!("CHR-RAM:      {}\n", format_size(h.chr_ram_size));
+    text += &format!("CHR-NVRAM:    {}\n", format_size(h.chr_nvram_size));
+    text += &format!("Mirroring:    {:?}\n", h.mirroring);
+    text += &format!("Battery:      {}\n", yes_no(h.battery));
+    text += &format!("Trainer:      {}\n", yes_no(h.trainer));
+    text += &format!("Console:      {}\n", match h.console_type
+    {
+        ConsoleType::Nes => String::from("NES/Famicom"),
+        ConsoleType::VsSystem { ppu, hardware } => format!("Vs. System (PPU type {}, hardware type {})", ppu, hardware),
+        ConsoleType::Playchoice => String::from("PlayChoice-10"),
+        ConsoleType::Extended(console) => format!("extended type {:#03X}", console),
+    });
+    text += &format!("Timing:       {}\n", match h.timing
+    {
+        Timing::Ntsc => "NTSC",
+        Timing::Pal => "PAL",
+        Timing::MultiRegion => "multi-region",
+        Timing::Dendy => "Dendy",
+    });
+    if h.format == HeaderFormat::Nes2
+    {
+        text += &format!("Misc ROMs:    {}\n", h.misc_rom_count);
+        text += &format!("Expansion:    {:#04X} ({})\n", h.expansion_device, expansion_device_name(h.expansion_device));
+    }
+    return text;
 }
@@ -0,0 +1,23 @@
+// rustNES core: the 6502 decoder and CPU, the NES bus, cartridges and mappers.
+// The rustNES_Disassembler binary is a thin front end over this crate.
+
+// returns are spelled out throughout the crate
+#![allow(clippy::needless_return)]
+
+pub mod file_handling;
+pub mod opcode;
+pub mod header;
+pub mod cpu;
+pub mod bus;
+pub mod trace;
+pub mod cartridge;
+pub mod mapper;
+
+#[cfg(test)]
+mod cpu_tests;
+#[cfg(test)]
+mod rom_tests;
+#[cfg(test)]
+mod mapper_tests;
+#[cfg(test)]
+mod trace_tests;
@@ -1,60 +1,354 @@
-mod file_handling;
-mod opcode;
-mod header;
-mod cpu;
+#![allow(clippy::needless_return)]
 
+use rustnes::bus::{Bus, NesBus};
+use rustnes::cpu::CPU;
+use rustnes::file_handling::*;
+use rustnes::header;
+use rustnes::opcode;
+use rustnes::trace;
+
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::process;
 use std::time::Duration;
 use std::thread;
 
-fn main()
+static USAGE: &str = "\
+usage: rustNES_Disassembler <command> <rom> [options]
+
+commands:
+    info <rom>                          print the iNES header
+    disasm <rom> [--bank N] [--from ADDR --to ADDR] [--format F]
+                                        disassemble a 16KB PRG-ROM bank
+    trace <rom> [--steps N] [--format F]
+                                        nestest style trace from the reset vector
+    run <rom> [--steps N] [--delay MS]  step the cpu, dumping registers after each instruction
+    nestest <rom> <log>                 compare a nestest.nes run against its reference log
+
+options:
+    --output FILE       write to FILE instead of stdout
+    --format text|csv   output format, disasm and trace only (default text)
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format
 {
-    let mut op_desc: String;
-    let mut op_code: u8;
+    Text,
+    Csv,
+}
 
-    let mut cpu = cpu::init_cpu();
+#[derive(Debug, Clone)]
+struct Options
+{
+    command: String,
+    rom: String,
+    log: Option<String>, // reference log for nestest
+    steps: Option<usize>,
+    delay: u64, // milliseconds between steps of run
+    output: Option<String>,
+    format: Format,
+    bank: usize,
+    from: Option<u16>,
+    to: Option<u16>,
+}
 
-    let path = String::from("SMB.nes");
-    let f = file_handling::open_file(path);
+// accepts 0x8000, $8000 or plain hex
+fn parse_address(text: &str) -> Result<u16, String>
+{
+    let digits = text.trim_start_matches("0x").trim_start_matches("0X").trim_start_matches('$');
+    return u16::from_str_radix(digits, 16).map_err(|_| format!("bad address: {}", text));
+}
 
-    let buffer = file_handling::store_file(f);
-    let mut list_of_opcodes = Vec::new();
-    let mut cur_opcode: opcode::Opcode;
+fn parse_number<T: std::str::FromStr>(name: &str, text: &str) -> Result<T, String>
+{
+    return text.parse().map_err(|_| format!("{} must be a number, got {}", name, text));
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String>
+{
+    let mut positional = Vec::new();
+    let mut options = Options
+    {
+        command: String::new(),
+        rom: String::new(),
+        log: None,
+        steps: None,
+        delay: 0,
+        output: None,
+        format: Format::Text,
+        bank: 0,
+        from: None,
+        to: None,
+    };
 
+    let mut format_given = false;
+    let mut i = 0;
+    while i < args.len()
+    {
+        let arg = &args[i];
+        if arg.starts_with("--")
+        {
+            let value = match args.get(i + 1)
+            {
+                Some(value) => value,
+                None => return Err(format!("{} needs a value", arg)),
+            };
+            match arg.as_str()
+            {
+                "--steps" => options.steps = Some(parse_number("--steps", value)?),
+                "--delay" => options.delay = parse_number("--delay", value)?,
+                "--bank" => options.bank = parse_number("--bank", value)?,
+                "--from" => options.from = Some(parse_address(value)?),
+                "--to" => options.to = Some(parse_address(value)?),
+                "--output" => options.output = Some(value.clone()),
+                "--format" =>
+                {
+                    options.format = match value.as_str()
+                    {
+                        "text" => Format::Text,
+                        "csv" => Format::Csv,
+                        _ => return Err(format!("unknown format: {}", value)),
+                    };
+                    format_given = true;
+                },
+                _ => return Err(format!("unknown option: {}", arg)),
+            }
+            i += 2;
+        }
+        else
+        {
+            positional.push(arg.clone());
+            i += 1;
+        }
+    }
 
-    for element in buffer.iter()
+    if positional.len() < 2
     {
-        list_of_opcodes.push(opcode::build_opcode(*element));
+        return Err(String::from("missing command or rom"));
     }
+    options.command = positional[0].clone();
+    options.rom = positional[1].clone();
+    options.log = positional.get(2).cloned();
+
+    match options.command.as_str()
+    {
+        "info" | "disasm" | "trace" | "run" => (),
+        "nestest" if options.log.is_some() => (),
+        "nestest" => return Err(String::from("nestest needs a reference log")),
+        _ => return Err(format!("unknown command: {}", options.command)),
+    }
+    if format_given && !matches!(options.command.as_str(), "disasm" | "trace")
+    {
+        return Err(format!("{} does not take --format", options.command));
+    }
+    return Ok(options);
+}
+
+fn open_output(options: &Options) -> Result<Box<dyn Write>, Box<dyn Error>>
+{
+    return match options.output
+    {
+        Some(ref path) => Ok(Box::new(File::create(path)?)),
+        None => Ok(Box::new(io::stdout())),
+    };
+}
+
+fn run_info(options: &Options, out: &mut dyn Write) -> Result<(), Box<dyn Error>>
+{
+    let rom = load_rom(options.rom.clone())?;
+    write!(out, "{}", header::format_header(rom.get_header()))?;
+    return Ok(());
+}
+
+// Linear sweep over one 16KB PRG-ROM bank. The last bank is shown in the
+// fixed $C000 window and every other bank at $8000, as NROM and most
+// bank switching boards map them after reset.
+fn run_disasm(options: &Options, out: &mut dyn Write) -> Result<(), Box<dyn Error>>
+{
+    let rom = load_rom(options.rom.clone())?;
+
+    let banks = rom.get_prg_rom().len() / 0x4000;
+    if options.bank >= banks
+    {
+        return Err(format!("bank {} out of range, the rom has {} PRG banks", options.bank, banks).into());
+    }
+    let start = options.bank * 0x4000;
+    let bank = &rom.get_prg_rom()[start..start + 0x4000];
+    let base: u16 = if options.bank == banks - 1 { 0xC000 } else { 0x8000 };
+
+    let from = options.from.unwrap_or(base);
+    let to = options.to.unwrap_or(base + 0x3FFF);
+    if from < base || to > base + 0x3FFF || from > to
+    {
+        return Err(format!("range must lie within {:#06X}-{:#06X}", base, base + 0x3FFF).into());
+    }
+
+    if options.format == Format::Csv
+    {
+        writeln!(out, "address,bytes,instruction")?;
+    }
+    let mut pc = from as usize;
+    while pc <= to as usize
+    {
+        let offset = pc - base as usize;
+        let code = bank[offset];
+        let length = opcode::decode(code).length;
+        let operands: Vec<u8> = (1..length).map(|i| *bank.get(offset + i).unwrap_or(&0)).collect();
+        let first_byte = *operands.first().unwrap_or(&0);
+        let second_byte = *operands.get(1).unwrap_or(&0);
+
+        let bytes: Vec<String> = bank[offset..(offset + length).min(0x4000)].iter()
+            .map(|byte| format!("{:02X}", byte)).collect();
+        let text = opcode::disassemble(pc as u16, code, first_byte, second_byte);
+        match options.format
+        {
+            Format::Text => writeln!(out, "{:04X}  {:<8}  {}", pc, bytes.join(" "), text)?,
+            Format::Csv => writeln!(out, "{:04X},{},\"{}\"", pc, bytes.join(" "), text)?,
+        }
+        pc += length;
+    }
+    return Ok(());
+}
+
+fn csv_trace_line(cpu: &mut CPU<NesBus>) -> String
+{
+    let pc = cpu.get_pc();
+    let code = cpu.get_bus_mut().peek(pc);
+    let first_byte = cpu.get_bus_mut().peek(pc.wrapping_add(1));
+    let second_byte = cpu.get_bus_mut().peek(pc.wrapping_add(2));
+    return format!("{:04X},\"{}\",{:02X},{:02X},{:02X},{:02X},{:02X},{}",
+                   pc, opcode::disassemble(pc, code, first_byte, second_byte),
+                   cpu.get_a(), cpu.get_x(), cpu.get_y(), cpu.get_p(), cpu.get_s(), cpu.get_cycles());
+}
+
+// one line per instruction, nestest style unless csv was asked for
+fn run_trace(options: &Options, out: &mut dyn Write) -> Result<(), Box<dyn Error>>
+{
+    let mut cpu = load_cpu(options.rom.clone())?;
+    if options.format == Format::Csv
+    {
+        writeln!(out, "pc,instruction,a,x,y,p,sp,cycles")?;
+    }
+    for _ in 0..options.steps.unwrap_or(0x1000)
+    {
+        match options.format
+        {
+            Format::Text => writeln!(out, "{}", trace::trace_line(&mut cpu))?,
+            Format::Csv => writeln!(out, "{}", csv_trace_line(&mut cpu))?,
+        }
+        if cpu.is_halted()
+        {
+            break;
+        }
+        cpu.step();
+    }
+    return Ok(());
+}
+
+// nestest.nes runs its automated tests from 0xC000 without a PPU,
+// so its log can be checked line by line
+fn run_nestest(options: &Options, out: &mut dyn Write) -> Result<(), Box<dyn Error>>
+{
+    let mut cpu = load_cpu(options.rom.clone())?;
+    cpu.set_pc(0xC000);
+
+    let log = store_file(open_file(options.log.clone().unwrap_or_default())?)?;
+    let reference = String::from_utf8_lossy(&log);
+
+    match trace::compare_with_log(&mut cpu, &reference)
+    {
+        Some(divergence) =>
+        {
+            writeln!(out, "Trace diverges at line {}", divergence.line)?;
+            writeln!(out, "expected: {}", divergence.expected)?;
+            writeln!(out, "actual:   {}", divergence.actual)?;
+        },
+        None => writeln!(out, "Trace matches the reference log")?,
+    }
+    return Ok(());
+}
+
+fn run_debug(options: &Options, out: &mut dyn Write) -> Result<(), Box<dyn Error>>
+{
+    let mut op_desc: String;
+    let mut op_text: String;
+    let mut op_code: u8;
+    let mut op_pc: u16;
+
+    let mut cpu = load_cpu(options.rom.clone())?;
 
-    let h = header::set_header(list_of_opcodes.clone());
-    let mut i = 0x10;
     let mut n = 0x0;
-    while i <= 0xFFF
-    {
-        cur_opcode = list_of_opcodes[i].clone();
-        op_desc = opcode::get_opcode_description(list_of_opcodes[i].clone());
-        op_code = opcode::get_opcode_code(list_of_opcodes[i].clone());
-        cpu.set_instruction(opcode::get_opcode_code(list_of_opcodes[i].clone()));
-        cpu.set_first_byte_of_interest(opcode::get_opcode_code(list_of_opcodes[i+1].clone()));
-        cpu.set_second_byte_of_interest(opcode::get_opcode_code(list_of_opcodes[i+2].clone()));
-
-        println!("{:#04X}: {:#04X} | {}", cpu.get_pc(), op_code, op_desc);
-        print!("\n");
-        cpu.execute_opcode(cur_opcode);
-        println!("Interrupt Flag: {:#04X}", cpu.get_interrupt_flag());
-        println!("Carry Flag: {:#04X}", cpu.get_carry_flag());
-        println!("Zero Flag: {:#04X}", cpu.get_zero_flag());
-        println!("Decimal Flag: {:#04X}", cpu.get_decimal_flag());
-        println!("Overflow Flag: {:#04X}", cpu.get_overflow_flag());
-        println!("Negative Flag: {:#04X}", cpu.get_negative_flag());
-        println!("A Register: {:#04X}", cpu.get_a());
-        println!("X Register: {:#04X}", cpu.get_x());
-        println!("Y Register: {:#04X}", cpu.get_y());
-        println!("S Register: {:#04X}", cpu.get_s());
-        println!("_____________________________________\n");
-        i = cpu.get_pc() as usize;
+    while options.steps.map(|steps| n < steps).unwrap_or(true)
+    {
+        op_pc = cpu.get_pc();
+        cpu.step();
+        op_code = cpu.get_instruction();
+        op_desc = opcode::get_opcode_description(opcode::build_opcode(op_code));
+        op_text = opcode::disassemble(op_pc, op_code,
+                                      cpu.get_first_byte_of_interest(),
+                                      cpu.get_second_byte_of_interest());
+
+        writeln!(out, "{:#06X}: {:#04X} | {:<12} | {}", op_pc, op_code, op_text, op_desc)?;
+        if cpu.is_halted()
+        {
+            writeln!(out, "CPU jammed by {:#04X} at {:#06X}", op_code, cpu.get_pc())?;
+            break;
+        }
+        writeln!(out)?;
+        writeln!(out, "Interrupt Flag: {:#04X}", cpu.get_interrupt_flag())?;
+        writeln!(out, "Carry Flag: {:#04X}", cpu.get_carry_flag())?;
+        writeln!(out, "Zero Flag: {:#04X}", cpu.get_zero_flag())?;
+        writeln!(out, "Decimal Flag: {:#04X}", cpu.get_decimal_flag())?;
+        writeln!(out, "Overflow Flag: {:#04X}", cpu.get_overflow_flag())?;
+        writeln!(out, "Negative Flag: {:#04X}", cpu.get_negative_flag())?;
+        writeln!(out, "A Register: {:#04X}", cpu.get_a())?;
+        writeln!(out, "X Register: {:#04X}", cpu.get_x())?;
+        writeln!(out, "Y Register: {:#04X}", cpu.get_y())?;
+        writeln!(out, "S Register: {:#04X}", cpu.get_s())?;
+        writeln!(out, "Cycles: {}", cpu.get_cycles())?;
+        writeln!(out, "_____________________________________\n")?;
         n += 1;
-        thread::sleep(Duration::from_millis(1000));
+        if options.delay > 0
+        {
+            thread::sleep(Duration::from_millis(options.delay));
+        }
     }
+    return Ok(());
+}
+
+fn main()
+{
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let options = match parse_args(&args)
+    {
+        Ok(options) => options,
+        Err(error) =>
+        {
+            eprintln!("{}\n\n{}", error, USAGE);
+            process::exit(2);
+        },
+    };
+
+    let result = open_output(&options).and_then(|mut out|
+    {
+        return match options.command.as_str()
+        {
+            "info" => run_info(&options, &mut *out),
+            "disasm" => run_disasm(&options, &mut *out),
+            "trace" => run_trace(&options, &mut *out),
+            "nestest" => run_nestest(&options, &mut *out),
+            "run" => run_debug(&options, &mut *out),
+            _ => Err(format!("unknown command: {}", options.command).into()),
+        };
+    });
 
+    if let Err(error) = result
+    {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
 }
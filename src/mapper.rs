@@ -0,0 +1,106 @@
+use std::fmt;
+
+use crate::header::*;
+
+mod nrom;
+mod mmc1;
+mod uxrom;
+mod cnrom;
+mod axrom;
+mod mmc3;
+mod mmc5;
+mod mmc5_audio;
+
+// Where a cartridge access ends up once the mapper has decoded it.
+// Offsets are into the cartridge's memories and wrap around their size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mapped
+{
+    PrgRom(usize),
+    PrgRam(usize),
+    Chr(usize), // CHR-ROM or CHR-RAM, whichever the board carries
+    Value(u8), // answered by the mapper itself, e.g. one of its registers
+    Ciram(usize), // the console's 2KB of nametable RAM, for boards that map nametables themselves
+    Nothing, // open bus on reads, ignored on writes
+}
+
+// The banking logic of a cartridge board. The cartridge owns the memories,
+// a mapper only decides which byte an address lands on and tracks whatever
+// registers the board exposes through writes.
+pub trait Mapper: fmt::Debug
+{
+    // CPU side, 0x4020 - 0xFFFF
+    fn cpu_read(&mut self, address: u16) -> Mapped;
+    // a write may just update mapper registers and return Nothing
+    fn cpu_write(&mut self, address: u16, val: u8) -> Mapped;
+    // read without side effects, for debuggers and tracing
+    fn cpu_peek(&mut self, address: u16) -> Mapped
+    {
+        return self.cpu_read(address);
+    }
+
+    // PPU side, 0x0000 - 0x1FFF pattern tables. The PPU passes every address
+    // it puts on its bus, nametable fetches included, so boards that watch the
+    // address lines see them all; anything above the pattern tables maps to Nothing.
+    fn ppu_read(&mut self, address: u16) -> Mapped;
+    fn ppu_write(&mut self, address: u16, val: u8) -> Mapped;
+
+    fn get_mirroring(&self) -> Mirroring;
+
+    // Discrete logic boards drive the data bus from ROM while the CPU writes
+    // their register, so the value latched is the AND of both.
+    fn has_bus_conflicts(&self) -> bool
+    {
+        return false;
+    }
+
+    // level the board drives onto the CPU IRQ line
+    fn irq_pending(&self) -> bool
+    {
+        return false;
+    }
+
+    // called once per CPU cycle, for boards with their own timers
+    fn cpu_clock(&mut self)
+    {
+    }
+
+    // expansion sound on the APU's 0.0 - 1.0 output scale, mixed in by NesBus::audio_sample
+    fn audio_output(&self) -> f32
+    {
+        return 0.0;
+    }
+}
+
+// NES 2.0 submappers 1 and 2 of the discrete logic boards say whether the
+// board has bus conflicts, anything else falls back to what most carts did
+fn bus_conflicts(h: &Header, default: bool) -> bool
+{
+    return match (h.get_format(), h.get_submapper())
+    {
+        (HeaderFormat::Nes2, 1) => false,
+        (HeaderFormat::Nes2, 2) => true,
+        _ => default,
+    };
+}
+
+pub fn is_supported_mapper(mapper: u16) -> bool
+{
+    return matches!(mapper, 0 | 1 | 2 | 3 | 4 | 5 | 7);
+}
+
+// The board named by the header, or None for mappers we do not emulate
+pub fn create_mapper(h: &Header) -> Option<Box<dyn Mapper>>
+{
+    return match h.get_mapper()
+    {
+        0 => Some(Box::new(nrom::init_nrom(h))),
+        1 => Some(Box::new(mmc1::init_mmc1(h))),
+        2 => Some(Box::new(uxrom::init_uxrom(h))),
+        3 => Some(Box::new(cnrom::init_cnrom(h))),
+        4 => Some(Box::new(mmc3::init_mmc3(h))),
+        5 => Some(Box::new(mmc5::init_mmc5(h))),
+        7 => Some(Box::new(axrom::init_axrom(h))),
+        _ => None,
+    };
+}
@@ -0,0 +1,72 @@
+use crate::header::*;
+use crate::mapper::{bus_conflicts, Mapped, Mapper};
+
+// AxROM, mapper 7 (ANROM, AMROM, AOROM).
+// Writes to 0x8000 - 0xFFFF select ---N-PPP: PPP is the 32KB PRG bank and
+// N the 1KB of VRAM every nametable shows. CHR is 8KB of RAM.
+#[derive(Debug, Clone)]
+pub struct Axrom
+{
+    bank_select: u8,
+    bus_conflicts: bool,
+}
+
+pub fn init_axrom(h: &Header) -> Axrom
+{
+    let mapper = Axrom
+    {
+        bank_select: 0,
+        // ANROM has none and some of its games depend on that
+        bus_conflicts: bus_conflicts(h, false),
+    };
+    return mapper;
+}
+
+impl Mapper for Axrom
+{
+    fn cpu_read(&mut self, address: u16) -> Mapped
+    {
+        return match address
+        {
+            0x8000..=0xFFFF => Mapped::PrgRom((self.bank_select & 0b111) as usize * 0x8000 + (address - 0x8000) as usize),
+            _               => Mapped::Nothing,
+        };
+    }
+
+    fn cpu_write(&mut self, address: u16, val: u8) -> Mapped
+    {
+        if address >= 0x8000
+        {
+            self.bank_select = val;
+        }
+        return Mapped::Nothing;
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Mapped
+    {
+        return match address
+        {
+            0x0000..=0x1FFF => Mapped::Chr(address as usize),
+            _               => Mapped::Nothing,
+        };
+    }
+
+    fn ppu_write(&mut self, address: u16, _val: u8) -> Mapped
+    {
+        return self.ppu_read(address);
+    }
+
+    fn get_mirroring(&self) -> Mirroring
+    {
+        if (self.bank_select & 0b10000) != 0
+        {
+            return Mirroring::SingleScreenUpper;
+        }
+        return Mirroring::SingleScreenLower;
+    }
+
+    fn has_bus_conflicts(&self) -> bool
+    {
+        return self.bus_conflicts;
+    }
+}
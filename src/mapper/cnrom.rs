@@ -0,0 +1,69 @@
+use crate::header::*;
+use crate::mapper::{bus_conflicts, Mapped, Mapper};
+
+// CNROM, mapper 3.
+// PRG-ROM is laid out as on NROM, any write to 0x8000 - 0xFFFF
+// picks the 8KB CHR-ROM bank.
+#[derive(Debug, Clone)]
+pub struct Cnrom
+{
+    chr_bank: u8,
+    mirroring: Mirroring, // soldered on the board
+    bus_conflicts: bool,
+}
+
+pub fn init_cnrom(h: &Header) -> Cnrom
+{
+    let mapper = Cnrom
+    {
+        chr_bank: 0,
+        mirroring: h.get_mirroring(),
+        bus_conflicts: bus_conflicts(h, true),
+    };
+    return mapper;
+}
+
+impl Mapper for Cnrom
+{
+    fn cpu_read(&mut self, address: u16) -> Mapped
+    {
+        return match address
+        {
+            0x8000..=0xFFFF => Mapped::PrgRom((address - 0x8000) as usize), // 16KB boards wrap onto themselves
+            _               => Mapped::Nothing,
+        };
+    }
+
+    fn cpu_write(&mut self, address: u16, val: u8) -> Mapped
+    {
+        if address >= 0x8000
+        {
+            self.chr_bank = val;
+        }
+        return Mapped::Nothing;
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Mapped
+    {
+        return match address
+        {
+            0x0000..=0x1FFF => Mapped::Chr(self.chr_bank as usize * 0x2000 + address as usize),
+            _               => Mapped::Nothing,
+        };
+    }
+
+    fn ppu_write(&mut self, address: u16, _val: u8) -> Mapped
+    {
+        return self.ppu_read(address);
+    }
+
+    fn get_mirroring(&self) -> Mirroring
+    {
+        return self.mirroring;
+    }
+
+    fn has_bus_conflicts(&self) -> bool
+    {
+        return self.bus_conflicts;
+    }
+}
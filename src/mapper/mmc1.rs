@@ -0,0 +1,197 @@
+use crate::header::*;
+use crate::mapper::{Mapped, Mapper};
+
+// the shift register starts out holding only this marker bit, it reaches
+// bit 0 once four bits have been shifted in behind it
+static SHIFT_RESET: u8 = 0b10000;
+
+// MMC1, mapper 1 (SxROM boards).
+// Registers are loaded one bit at a time through writes to 0x8000 - 0xFFFF,
+// the fifth write copies the shift register into the register picked by
+// address bits 13 and 14:
+// 0x8000 control    CPPMM  C: 4KB CHR mode, PP: PRG mode, MM: mirroring
+// 0xA000 CHR bank 0 (low half of the pattern tables, or all 8KB)
+// 0xC000 CHR bank 1 (high half, 4KB mode only)
+// 0xE000 PRG bank   RPPPP  R: PRG-RAM disable
+//
+// 512KB SUROM/SXROM boards run out of PRG bank bits, so they take the outer
+// 256KB half from bit 4 of the CHR bank register, and SXROM/SOROM pick one
+// of their 8KB PRG-RAM pages with bits 2-3. In 4KB CHR mode the register
+// is whichever one PPU A12 currently selects, so the bits can change while
+// the PPU renders.
+#[derive(Debug, Clone)]
+pub struct Mmc1
+{
+    shift: u8,
+    control: u8,
+    chr_bank_0: u8,
+    chr_bank_1: u8,
+    prg_bank: u8,
+    prg_rom_size: usize,
+    prg_ram_size: usize,
+    chr_a12: bool, // PPU A12 as of the last PPU access
+}
+
+pub fn init_mmc1(h: &Header) -> Mmc1
+{
+    let mapper = Mmc1
+    {
+        shift: SHIFT_RESET,
+        control: 0b01100, // the last bank is fixed at power up
+        chr_bank_0: 0,
+        chr_bank_1: 0,
+        prg_bank: 0,
+        prg_rom_size: h.get_prg_rom_size(),
+        prg_ram_size: h.get_prg_ram_size() + h.get_prg_nvram_size(),
+        chr_a12: false,
+    };
+    return mapper;
+}
+
+impl Mmc1
+{
+    fn load_register(&mut self, address: u16, val: u8)
+    {
+        // any write with bit 7 set clears the shift register and fixes the last bank
+        if (val & 0b10000000) != 0
+        {
+            self.shift = SHIFT_RESET;
+            self.control |= 0b01100;
+            return;
+        }
+
+        let full = (self.shift & 0b1) != 0;
+        self.shift = (self.shift >> 1) | ((val & 0b1) << 4);
+        if full
+        {
+            let value = self.shift;
+            match address
+            {
+                0x8000..=0x9FFF => self.control = value,
+                0xA000..=0xBFFF => self.chr_bank_0 = value,
+                0xC000..=0xDFFF => self.chr_bank_1 = value,
+                _               => self.prg_bank = value,
+            }
+            self.shift = SHIFT_RESET;
+        }
+    }
+
+    // the CHR bank register driving the board's extra lines
+    fn selected_chr_bank(&self) -> u8
+    {
+        if (self.control & 0b10000) != 0 && self.chr_a12
+        {
+            return self.chr_bank_1;
+        }
+        return self.chr_bank_0;
+    }
+
+    // offset of the selected 256KB half on 512KB boards
+    fn prg_outer_bank(&self) -> usize
+    {
+        if self.prg_rom_size > 0x40000
+        {
+            return ((self.selected_chr_bank() & 0b10000) as usize) << 14;
+        }
+        return 0;
+    }
+
+    fn prg_rom_offset(&self, address: u16) -> usize
+    {
+        let bank = (self.prg_bank & 0b01111) as usize;
+        let last = (self.prg_rom_size.min(0x40000) / 0x4000).saturating_sub(1);
+        let window = (address & 0x3FFF) as usize;
+        let upper = address >= 0xC000;
+
+        let bank_16k = match (self.control >> 2) & 0b11
+        {
+            // 32KB mode ignores the low bank bit
+            0 | 1 => (bank & !0b1) + upper as usize,
+            // first bank fixed at 0x8000, 0xC000 switchable
+            2 => if upper { bank } else { 0 },
+            // 0x8000 switchable, last bank fixed at 0xC000
+            _ => if upper { last } else { bank },
+        };
+        return self.prg_outer_bank() + bank_16k * 0x4000 + window;
+    }
+
+    fn prg_ram_enabled(&self) -> bool
+    {
+        return (self.prg_bank & 0b10000) == 0;
+    }
+
+    fn prg_ram_offset(&self, address: u16) -> usize
+    {
+        let page = match self.prg_ram_size
+        {
+            0x8000 => ((self.selected_chr_bank() >> 2) & 0b11) as usize, // SXROM
+            0x4000 => ((self.selected_chr_bank() >> 3) & 0b1) as usize, // SOROM
+            _ => 0,
+        };
+        return page * 0x2000 + (address - 0x6000) as usize;
+    }
+
+    fn chr_offset(&self, address: u16) -> usize
+    {
+        if (self.control & 0b10000) == 0
+        {
+            // 8KB mode ignores the low bank bit
+            return (self.chr_bank_0 & !0b1) as usize * 0x1000 + address as usize;
+        }
+        let bank = if address < 0x1000 { self.chr_bank_0 } else { self.chr_bank_1 };
+        return bank as usize * 0x1000 + (address & 0x0FFF) as usize;
+    }
+}
+
+impl Mapper for Mmc1
+{
+    fn cpu_read(&mut self, address: u16) -> Mapped
+    {
+        return match address
+        {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => Mapped::PrgRam(self.prg_ram_offset(address)),
+            0x8000..=0xFFFF => Mapped::PrgRom(self.prg_rom_offset(address)),
+            _               => Mapped::Nothing,
+        };
+    }
+
+    fn cpu_write(&mut self, address: u16, val: u8) -> Mapped
+    {
+        return match address
+        {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => Mapped::PrgRam(self.prg_ram_offset(address)),
+            0x8000..=0xFFFF =>
+            {
+                self.load_register(address, val);
+                Mapped::Nothing
+            },
+            _               => Mapped::Nothing,
+        };
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Mapped
+    {
+        self.chr_a12 = (address & 0x1000) != 0;
+        return match address
+        {
+            0x0000..=0x1FFF => Mapped::Chr(self.chr_offset(address)),
+            _               => Mapped::Nothing,
+        };
+    }
+
+    fn ppu_write(&mut self, address: u16, _val: u8) -> Mapped
+    {
+        return self.ppu_read(address);
+    }
+
+    fn get_mirroring(&self) -> Mirroring
+    {
+        return match self.control & 0b11
+        {
+            0 => Mirroring::SingleScreenLower,
+            1 => Mirroring::SingleScreenUpper,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        };
+    }
+}
@@ -0,0 +1,222 @@
+use crate::header::*;
+use crate::mapper::{Mapped, Mapper};
+
+// A12 has to stay low for this many PPU accesses before a rise clocks the
+// scanline counter. The two garbage nametable fetches between sprite
+// pattern fetches are too short, the gap after background fetches is not.
+static A12_FILTER: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Revision
+{
+    // MMC3A and older: the IRQ fires only when the counter is decremented
+    // or reloaded to 0, so a latch of 0 fires once
+    A,
+    // MMC3B/C: the IRQ fires whenever the counter is 0 after a clock,
+    // so a latch of 0 fires every scanline
+    B,
+}
+
+// MMC3, mapper 4 (TxROM boards).
+// 0x8000 bank select   CP---RRR  C: CHR A12 inversion, P: PRG mode, RRR: target of the next bank data
+// 0x8001 bank data     R0/R1 2KB CHR, R2-R5 1KB CHR, R6/R7 8KB PRG
+// 0xA000 mirroring     0 vertical, 1 horizontal
+// 0xA001 PRG-RAM       EW------  E: chip enable, W: write protect
+// 0xC000 IRQ latch, 0xC001 IRQ reload
+// 0xE000 IRQ disable and acknowledge, 0xE001 IRQ enable
+// Every register is mirrored through its 8KB window by even/odd address.
+#[derive(Debug, Clone)]
+pub struct Mmc3
+{
+    bank_select: u8,
+    banks: [u8; 8],
+    mirroring: Mirroring,
+    four_screen: bool, // the board carries its own VRAM, mirroring writes do nothing
+    prg_ram_protect: u8,
+    prg_banks: usize, // number of 8KB PRG banks
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_pending: bool,
+    a12_low_count: u8,
+    revision: Revision,
+}
+
+pub fn init_mmc3(h: &Header) -> Mmc3
+{
+    let revision = match (h.get_format(), h.get_submapper())
+    {
+        (HeaderFormat::Nes2, 4) => Revision::A,
+        _ => Revision::B,
+    };
+    let mapper = Mmc3
+    {
+        bank_select: 0,
+        banks: [0, 2, 4, 5, 6, 7, 0, 1],
+        mirroring: h.get_mirroring(),
+        four_screen: h.get_mirroring() == Mirroring::FourScreen,
+        prg_ram_protect: 0b10000000,
+        prg_banks: (h.get_prg_rom_size() / 0x2000).max(2),
+        irq_latch: 0,
+        irq_counter: 0,
+        irq_reload: false,
+        irq_enabled: false,
+        irq_pending: false,
+        a12_low_count: 0,
+        revision,
+    };
+    return mapper;
+}
+
+impl Mmc3
+{
+    fn prg_rom_offset(&self, address: u16) -> usize
+    {
+        let second_last = self.prg_banks - 2;
+        let swapped = (self.bank_select & 0b01000000) != 0;
+        let bank = match (address, swapped)
+        {
+            (0x8000..=0x9FFF, false) => self.banks[6] as usize,
+            (0x8000..=0x9FFF, true)  => second_last,
+            (0xA000..=0xBFFF, _)     => self.banks[7] as usize,
+            (0xC000..=0xDFFF, false) => second_last,
+            (0xC000..=0xDFFF, true)  => self.banks[6] as usize,
+            _                        => self.prg_banks - 1,
+        };
+        return (bank & 0b00111111) * 0x2000 + (address & 0x1FFF) as usize;
+    }
+
+    fn chr_offset(&self, address: u16) -> usize
+    {
+        // inversion swaps the 2KB and 1KB halves of the pattern tables
+        let address = if (self.bank_select & 0b10000000) != 0 { address ^ 0x1000 } else { address };
+        return match address
+        {
+            0x0000..=0x07FF => (self.banks[0] & !0b1) as usize * 0x400 + (address & 0x07FF) as usize,
+            0x0800..=0x0FFF => (self.banks[1] & !0b1) as usize * 0x400 + (address & 0x07FF) as usize,
+            0x1000..=0x13FF => self.banks[2] as usize * 0x400 + (address & 0x03FF) as usize,
+            0x1400..=0x17FF => self.banks[3] as usize * 0x400 + (address & 0x03FF) as usize,
+            0x1800..=0x1BFF => self.banks[4] as usize * 0x400 + (address & 0x03FF) as usize,
+            _               => self.banks[5] as usize * 0x400 + (address & 0x03FF) as usize,
+        };
+    }
+
+    fn prg_ram_enabled(&self) -> bool
+    {
+        return (self.prg_ram_protect & 0b10000000) != 0;
+    }
+
+    fn prg_ram_writable(&self) -> bool
+    {
+        return self.prg_ram_enabled() && (self.prg_ram_protect & 0b01000000) == 0;
+    }
+
+    fn clock_irq_counter(&mut self)
+    {
+        let old_counter = self.irq_counter;
+        let reloaded = self.irq_reload;
+        if self.irq_counter == 0 || self.irq_reload
+        {
+            self.irq_counter = self.irq_latch;
+        }
+        else
+        {
+            self.irq_counter -= 1;
+        }
+        self.irq_reload = false;
+
+        let fire = match self.revision
+        {
+            Revision::A => self.irq_counter == 0 && (old_counter != 0 || reloaded),
+            Revision::B => self.irq_counter == 0,
+        };
+        if fire && self.irq_enabled
+        {
+            self.irq_pending = true;
+        }
+    }
+
+    // the counter is clocked by rises of PPU A12 that survive the filter
+    fn watch_a12(&mut self, address: u16)
+    {
+        if (address & 0x1000) == 0
+        {
+            self.a12_low_count = self.a12_low_count.saturating_add(1);
+            return;
+        }
+        if self.a12_low_count >= A12_FILTER
+        {
+            self.clock_irq_counter();
+        }
+        self.a12_low_count = 0;
+    }
+}
+
+impl Mapper for Mmc3
+{
+    fn cpu_read(&mut self, address: u16) -> Mapped
+    {
+        return match address
+        {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => Mapped::PrgRam((address - 0x6000) as usize),
+            0x8000..=0xFFFF => Mapped::PrgRom(self.prg_rom_offset(address)),
+            _               => Mapped::Nothing,
+        };
+    }
+
+    fn cpu_write(&mut self, address: u16, val: u8) -> Mapped
+    {
+        let odd = (address & 0b1) != 0;
+        match (address, odd)
+        {
+            (0x6000..=0x7FFF, _) if self.prg_ram_writable() => return Mapped::PrgRam((address - 0x6000) as usize),
+            (0x8000..=0x9FFF, false) => self.bank_select = val,
+            (0x8000..=0x9FFF, true)  => self.banks[(self.bank_select & 0b111) as usize] = val,
+            (0xA000..=0xBFFF, false) if !self.four_screen =>
+            {
+                self.mirroring = if (val & 0b1) != 0 { Mirroring::Horizontal } else { Mirroring::Vertical };
+            },
+            (0xA000..=0xBFFF, true)  => self.prg_ram_protect = val,
+            (0xC000..=0xDFFF, false) => self.irq_latch = val,
+            (0xC000..=0xDFFF, true)  =>
+            {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            },
+            (0xE000..=0xFFFF, false) =>
+            {
+                self.irq_enabled = false;
+                self.irq_pending = false;
+            },
+            (0xE000..=0xFFFF, true)  => self.irq_enabled = true,
+            _ => (),
+        }
+        return Mapped::Nothing;
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Mapped
+    {
+        self.watch_a12(address);
+        return match address
+        {
+            0x0000..=0x1FFF => Mapped::Chr(self.chr_offset(address)),
+            _               => Mapped::Nothing,
+        };
+    }
+
+    fn ppu_write(&mut self, address: u16, _val: u8) -> Mapped
+    {
+        return self.ppu_read(address);
+    }
+
+    fn get_mirroring(&self) -> Mirroring
+    {
+        return self.mirroring;
+    }
+
+    fn irq_pending(&self) -> bool
+    {
+        return self.irq_pending;
+    }
+}
@@ -0,0 +1,468 @@
+use crate::header::*;
+use crate::mapper::{Mapped, Mapper};
+use crate::mapper::mmc5_audio::*;
+
+// MMC5, mapper 5 (ExROM boards).
+// 0x5000 - 0x5015 expansion audio, see mmc5_audio
+// 0x5100 PRG mode  0: 32KB, 1: 16KB + 16KB, 2: 16KB + 8KB + 8KB, 3: 4 x 8KB
+// 0x5101 CHR mode  0: 8KB, 1: 4KB, 2: 2KB, 3: 1KB
+// 0x5102 / 0x5103 PRG-RAM write protect, writable only while they hold 0b10 / 0b01
+// 0x5104 ExRAM mode  0: nametable, 1: extended attributes, 2: CPU RAM, 3: CPU ROM
+// 0x5105 nametable mapping, two bits per nametable  0: CIRAM 0, 1: CIRAM 1, 2: ExRAM, 3: fill
+// 0x5106 / 0x5107 fill mode tile and attribute
+// 0x5113 PRG-RAM bank at 0x6000
+// 0x5114 - 0x5117 PRG banks  RBBBBBBB  R: ROM (RAM when clear), 0x5117 is always ROM
+// 0x5120 - 0x5127 CHR banks for sprites, 0x5128 - 0x512B for the background of 8x16 sprite frames
+// 0x5130 upper CHR bank bits
+// 0x5200 - 0x5202 vertical split control, scroll and CHR bank
+// 0x5203 IRQ scanline, 0x5204 IRQ enable / status
+// 0x5205 / 0x5206 8x8 multiplier, reads back the 16 bit product
+// 0x5C00 - 0x5FFF 1KB ExRAM
+//
+// The board watches the PPU bus to know what is being fetched: three reads
+// of the same nametable address in a row only happen at the start of a
+// rendered scanline, and from there each fetch is counted.
+#[derive(Debug, Clone)]
+pub struct Mmc5
+{
+    prg_mode: u8,
+    chr_mode: u8,
+    prg_ram_protect: [u8; 2],
+    exram_mode: u8,
+    exram: [u8; 0x400],
+    nametable_mapping: u8,
+    fill_tile: u8,
+    fill_attribute: u8,
+    prg_ram_bank: u8,
+    prg_banks: [u8; 4],
+    chr_banks: [u16; 12],
+    chr_upper: u8,
+    chr_set_b_written: bool, // the background set was written last
+    split_control: u8,
+    split_scroll: u8,
+    split_bank: u8,
+    irq_scanline: u8,
+    irq_enabled: bool,
+    irq_pending: bool,
+    in_frame: bool,
+    scanline: u8,
+    multiplicand: u8,
+    multiplier: u8,
+    sprites_8x16: bool, // snooped from PPU_CTRL_REG1
+    last_ppu_address: u16,
+    nametable_repeats: u8,
+    fetch: u16, // PPU reads since the scanline was detected
+    ex_attribute: u8, // ExRAM byte latched by the last background nametable fetch
+    audio: Mmc5Audio,
+}
+
+pub fn init_mmc5(_h: &Header) -> Mmc5
+{
+    let mapper = Mmc5
+    {
+        prg_mode: 3,
+        chr_mode: 3,
+        prg_ram_protect: [0, 0],
+        exram_mode: 0,
+        exram: [0x0; 0x400],
+        nametable_mapping: 0,
+        fill_tile: 0,
+        fill_attribute: 0,
+        prg_ram_bank: 0,
+        prg_banks: [0, 0, 0, 0xFF], // the last bank is at 0xE000 at power up
+        chr_banks: [0; 12],
+        chr_upper: 0,
+        chr_set_b_written: false,
+        split_control: 0,
+        split_scroll: 0,
+        split_bank: 0,
+        irq_scanline: 0,
+        irq_enabled: false,
+        irq_pending: false,
+        in_frame: false,
+        scanline: 0,
+        multiplicand: 0xFF,
+        multiplier: 0xFF,
+        sprites_8x16: false,
+        last_ppu_address: 0,
+        nametable_repeats: 0,
+        fetch: 0,
+        ex_attribute: 0,
+        audio: init_mmc5_audio(),
+    };
+    return mapper;
+}
+
+// an attribute byte giving all four quadrants the same palette
+fn attribute_byte(palette: u8) -> u8
+{
+    return (palette & 0b11) * 0b01010101;
+}
+
+impl Mmc5
+{
+    fn prg_ram_writable(&self) -> bool
+    {
+        return self.prg_ram_protect == [0b10, 0b01];
+    }
+
+    fn prg_mapped(&self, address: u16) -> Mapped
+    {
+        // register and window size for the address in the current mode
+        let (register, size) = match (self.prg_mode, address)
+        {
+            (0, _)                   => (3, 0x8000),
+            (1, 0x8000..=0xBFFF)     => (1, 0x4000),
+            (1, _)                   => (3, 0x4000),
+            (2, 0x8000..=0xBFFF)     => (1, 0x4000),
+            (2, 0xC000..=0xDFFF)     => (2, 0x2000),
+            (2, _)                   => (3, 0x2000),
+            _                        => (((address - 0x8000) / 0x2000) as usize, 0x2000),
+        };
+        let bank = self.prg_banks[register];
+        let window = address as usize & (size - 1);
+        // banks count 8KB pages, larger windows ignore the low bits
+        let pages = size / 0x2000;
+        if register == 3 || (bank & 0b10000000) != 0
+        {
+            return Mapped::PrgRom(((bank & 0b01111111) as usize & !(pages - 1)) * 0x2000 + window);
+        }
+        return Mapped::PrgRam(((bank & 0b00000111) as usize & !(pages - 1)) * 0x2000 + window);
+    }
+
+    fn prg_ram_offset(&self, address: u16) -> usize
+    {
+        return (self.prg_ram_bank & 0b00000111) as usize * 0x2000 + (address - 0x6000) as usize;
+    }
+
+    fn sprite_fetch(&self) -> bool
+    {
+        return self.in_frame && (128..160).contains(&self.fetch);
+    }
+
+    // column and scanline of the background tile a rendering fetch belongs
+    // to, None for sprite fetches and accesses outside rendering.
+    // Columns 0 and 1 are fetched at the end of the scanline before.
+    fn background_tile(&self) -> Option<(u8, u8)>
+    {
+        if !self.in_frame
+        {
+            return None;
+        }
+        return match self.fetch
+        {
+            0..=127   => Some(((self.fetch / 4) as u8 + 2, self.scanline)),
+            160..=167 => Some((((self.fetch - 160) / 4) as u8, self.scanline.wrapping_add(1))),
+            _         => None,
+        };
+    }
+
+    fn in_split(&self, column: u8) -> bool
+    {
+        if (self.split_control & 0b10000000) == 0 || self.exram_mode > 1
+        {
+            return false;
+        }
+        let threshold = self.split_control & 0b00011111;
+        return if (self.split_control & 0b01000000) != 0 { column >= threshold } else { column < threshold };
+    }
+
+    fn chr_offset(&self, address: u16) -> usize
+    {
+        // with 8x16 sprites the background has its own banks, outside
+        // rendering whichever set was written last is used
+        let set_b = match (self.sprites_8x16, self.in_frame)
+        {
+            (false, _)    => false,
+            (true, true)  => !self.sprite_fetch(),
+            (true, false) => self.chr_set_b_written,
+        };
+        let size = 0x2000 >> self.chr_mode;
+        let slot = address as usize / size;
+        let register = (slot + 1) * (8 >> self.chr_mode) - 1;
+        let bank = if set_b { self.chr_banks[8 + (register & 0b11)] } else { self.chr_banks[register] };
+        return bank as usize * size + (address as usize & (size - 1));
+    }
+
+    fn new_scanline(&mut self)
+    {
+        self.fetch = 0;
+        if !self.in_frame
+        {
+            self.in_frame = true;
+            self.scanline = 0;
+            self.irq_pending = false;
+            return;
+        }
+        self.scanline = self.scanline.wrapping_add(1);
+        if self.scanline == self.irq_scanline
+        {
+            self.irq_pending = true;
+        }
+    }
+
+    fn end_frame(&mut self)
+    {
+        self.in_frame = false;
+        self.nametable_repeats = 0;
+    }
+
+    fn watch_fetches(&mut self, address: u16)
+    {
+        self.fetch = self.fetch.saturating_add(1);
+        let nametable = (0x2000..=0x2FFF).contains(&address);
+        if nametable && address == self.last_ppu_address
+        {
+            self.nametable_repeats += 1;
+            if self.nametable_repeats == 2
+            {
+                self.new_scanline();
+            }
+        }
+        else
+        {
+            self.nametable_repeats = 0;
+        }
+        self.last_ppu_address = address;
+    }
+
+    // 0x5105 entry for the nametable an address falls in
+    fn nametable_source(&self, address: u16) -> u8
+    {
+        return (self.nametable_mapping >> (((address >> 10) & 0b11) * 2)) & 0b11;
+    }
+
+    fn nametable_read(&self, address: u16) -> Mapped
+    {
+        let offset = (address & 0x03FF) as usize;
+        return match self.nametable_source(address)
+        {
+            0 => Mapped::Ciram(offset),
+            1 => Mapped::Ciram(0x400 + offset),
+            2 if self.exram_mode <= 1 => Mapped::Value(self.exram[offset]),
+            2 => Mapped::Value(0),
+            _ if offset >= 0x3C0 => Mapped::Value(attribute_byte(self.fill_attribute)),
+            _ => Mapped::Value(self.fill_tile),
+        };
+    }
+
+    // the split region draws from ExRAM with its own vertical scroll and CHR bank
+    fn split_fetch(&self, address: u16, column: u8, scanline: u8) -> Mapped
+    {
+        let y = (scanline as usize + self.split_scroll as usize) % 240;
+        let coarse_y = y / 8;
+        let column = (column & 0b11111) as usize;
+        if address < 0x2000
+        {
+            return Mapped::Chr(self.split_bank as usize * 0x1000 + (address & 0x0FF8) as usize + (y % 8));
+        }
+        if (address & 0x03FF) < 0x3C0
+        {
+            return Mapped::Value(self.exram[coarse_y * 32 + column]);
+        }
+        let attribute = self.exram[0x3C0 + (coarse_y / 4) * 8 + column / 4];
+        let shift = ((coarse_y & 0b10) << 1) | (column & 0b10);
+        return Mapped::Value(attribute_byte(attribute >> shift));
+    }
+
+    // extended attributes give every background tile its own palette and 4KB CHR bank
+    fn extended_attribute_fetch(&mut self, address: u16) -> Mapped
+    {
+        if address < 0x2000
+        {
+            let bank = (self.ex_attribute & 0b00111111) as usize | ((self.chr_upper as usize) << 6);
+            return Mapped::Chr(bank * 0x1000 + (address & 0x0FFF) as usize);
+        }
+        if (address & 0x03FF) >= 0x3C0
+        {
+            return Mapped::Value(attribute_byte(self.ex_attribute >> 6));
+        }
+        self.ex_attribute = self.exram[(address & 0x03FF) as usize];
+        return self.nametable_read(address);
+    }
+
+    fn status(&self) -> u8
+    {
+        return ((self.irq_pending as u8) << 7) | ((self.in_frame as u8) << 6);
+    }
+
+    fn product(&self) -> u16
+    {
+        return self.multiplicand as u16 * self.multiplier as u16;
+    }
+}
+
+impl Mapper for Mmc5
+{
+    fn cpu_read(&mut self, address: u16) -> Mapped
+    {
+        return match address
+        {
+            0x5015 => Mapped::Value(self.audio.read_status()),
+            0x5204 =>
+            {
+                let status = self.status();
+                self.irq_pending = false;
+                Mapped::Value(status)
+            },
+            0x5205 => Mapped::Value(self.product() as u8),
+            0x5206 => Mapped::Value((self.product() >> 8) as u8),
+            0x5C00..=0x5FFF if self.exram_mode >= 2 => Mapped::Value(self.exram[(address - 0x5C00) as usize]),
+            0x6000..=0x7FFF => Mapped::PrgRam(self.prg_ram_offset(address)),
+            0x8000..=0xFFFF =>
+            {
+                // fetching the NMI vector means the PPU has finished the frame
+                if address == 0xFFFA || address == 0xFFFB
+                {
+                    self.end_frame();
+                }
+                self.prg_mapped(address)
+            },
+            _ => Mapped::Nothing,
+        };
+    }
+
+    fn cpu_peek(&mut self, address: u16) -> Mapped
+    {
+        return match address
+        {
+            0x5204          => Mapped::Value(self.status()),
+            0x8000..=0xFFFF => self.prg_mapped(address),
+            _               => self.cpu_read(address),
+        };
+    }
+
+    fn cpu_write(&mut self, address: u16, val: u8) -> Mapped
+    {
+        match address
+        {
+            // the board sits on the CPU bus and snoops the PPU registers
+            0x2000..=0x3FFF => match address & 0x0007
+            {
+                0 => self.sprites_8x16 = (val & 0b00100000) != 0,
+                1 if (val & 0b00011000) == 0 => self.end_frame(),
+                _ => (),
+            },
+            0x5000..=0x5015 => self.audio.write(address, val),
+            0x5100 => self.prg_mode = val & 0b11,
+            0x5101 => self.chr_mode = val & 0b11,
+            0x5102 => self.prg_ram_protect[0] = val & 0b11,
+            0x5103 => self.prg_ram_protect[1] = val & 0b11,
+            0x5104 => self.exram_mode = val & 0b11,
+            0x5105 => self.nametable_mapping = val,
+            0x5106 => self.fill_tile = val,
+            0x5107 => self.fill_attribute = val & 0b11,
+            0x5113 => self.prg_ram_bank = val,
+            0x5114..=0x5117 => self.prg_banks[(address - 0x5114) as usize] = val,
+            0x5120..=0x512B =>
+            {
+                self.chr_banks[(address - 0x5120) as usize] = val as u16 | ((self.chr_upper as u16) << 8);
+                self.chr_set_b_written = address >= 0x5128;
+            },
+            0x5130 => self.chr_upper = val & 0b11,
+            0x5200 => self.split_control = val,
+            0x5201 => self.split_scroll = val,
+            0x5202 => self.split_bank = val,
+            0x5203 => self.irq_scanline = val,
+            0x5204 => self.irq_enabled = (val & 0b10000000) != 0,
+            0x5205 => self.multiplicand = val,
+            0x5206 => self.multiplier = val,
+            0x5C00..=0x5FFF =>
+            {
+                // as nametable memory it can only be written while rendering, 0 lands otherwise
+                let offset = (address - 0x5C00) as usize;
+                match self.exram_mode
+                {
+                    0 | 1 => self.exram[offset] = if self.in_frame { val } else { 0 },
+                    2 => self.exram[offset] = val,
+                    _ => (),
+                }
+            },
+            0x6000..=0x7FFF if self.prg_ram_writable() => return Mapped::PrgRam(self.prg_ram_offset(address)),
+            0x8000..=0xDFFF if self.prg_ram_writable() =>
+            {
+                if let Mapped::PrgRam(offset) = self.prg_mapped(address)
+                {
+                    return Mapped::PrgRam(offset);
+                }
+            },
+            _ => (),
+        }
+        return Mapped::Nothing;
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Mapped
+    {
+        self.watch_fetches(address);
+        if let Some((column, scanline)) = self.background_tile()
+        {
+            if self.in_split(column)
+            {
+                return self.split_fetch(address, column, scanline);
+            }
+            if self.exram_mode == 1 && address < 0x3000
+            {
+                return self.extended_attribute_fetch(address);
+            }
+        }
+        return match address
+        {
+            0x0000..=0x1FFF => Mapped::Chr(self.chr_offset(address)),
+            0x2000..=0x3EFF => self.nametable_read(address),
+            _               => Mapped::Nothing,
+        };
+    }
+
+    fn ppu_write(&mut self, address: u16, val: u8) -> Mapped
+    {
+        let offset = (address & 0x03FF) as usize;
+        return match address
+        {
+            0x0000..=0x1FFF => Mapped::Chr(self.chr_offset(address)),
+            0x2000..=0x3EFF => match self.nametable_source(address)
+            {
+                0 => Mapped::Ciram(offset),
+                1 => Mapped::Ciram(0x400 + offset),
+                2 =>
+                {
+                    if self.exram_mode <= 1
+                    {
+                        self.exram[offset] = val;
+                    }
+                    Mapped::Nothing
+                },
+                _ => Mapped::Nothing,
+            },
+            _ => Mapped::Nothing,
+        };
+    }
+
+    // the closest fixed layout; ExRAM and fill nametables only exist
+    // through ppu_read, so anything using them reports FourScreen
+    fn get_mirroring(&self) -> Mirroring
+    {
+        return match self.nametable_mapping
+        {
+            0x00 => Mirroring::SingleScreenLower,
+            0x55 => Mirroring::SingleScreenUpper,
+            0x44 => Mirroring::Vertical,
+            0x50 => Mirroring::Horizontal,
+            _    => Mirroring::FourScreen,
+        };
+    }
+
+    fn irq_pending(&self) -> bool
+    {
+        return self.irq_pending && self.irq_enabled;
+    }
+
+    fn cpu_clock(&mut self)
+    {
+        self.audio.clock();
+    }
+
+    fn audio_output(&self) -> f32
+    {
+        return self.audio.output();
+    }
+}
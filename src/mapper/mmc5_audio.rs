@@ -0,0 +1,241 @@
+// The MMC5's expansion sound: two pulse channels that work like the APU's
+// (minus the sweep unit) and an 8 bit PCM channel.
+// 0x5000 - 0x5003 pulse 1, 0x5004 - 0x5007 pulse 2
+//   +0 DDLCVVVV  D: duty, L: length halt / envelope loop, C: constant volume, V: volume / envelope period
+//   +2 timer low, +3 LLLLLTTT  L: length counter index, T: timer high
+// 0x5010 PCM mode, 0x5011 raw PCM level
+// 0x5015 length counter enables, reads back which channels are still playing
+
+// the MMC5 has no frame counter, envelopes and length counters run off a
+// fixed divider at roughly 240Hz
+static FRAME_PERIOD: u16 = 7457;
+
+static DUTY_SEQUENCES: [[u8; 8]; 4] =
+[
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+static LENGTH_TABLE: [u8; 32] =
+[
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14,
+    12, 16, 24, 18, 48, 20, 96, 22, 192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+#[derive(Debug, Clone)]
+struct Pulse
+{
+    enabled: bool,
+    duty: u8,
+    halt: bool, // also loops the envelope
+    constant_volume: bool,
+    volume: u8, // constant volume, or the envelope period
+    envelope_start: bool,
+    envelope_divider: u8,
+    envelope_decay: u8,
+    timer_period: u16,
+    timer: u16,
+    sequence: u8,
+    length: u8,
+}
+
+fn init_pulse() -> Pulse
+{
+    let pulse = Pulse
+    {
+        enabled: false,
+        duty: 0,
+        halt: false,
+        constant_volume: false,
+        volume: 0,
+        envelope_start: false,
+        envelope_divider: 0,
+        envelope_decay: 0,
+        timer_period: 0,
+        timer: 0,
+        sequence: 0,
+        length: 0,
+    };
+    return pulse;
+}
+
+impl Pulse
+{
+    fn write(&mut self, register: u16, val: u8)
+    {
+        match register
+        {
+            0 =>
+            {
+                self.duty = val >> 6;
+                self.halt = (val & 0b00100000) != 0;
+                self.constant_volume = (val & 0b00010000) != 0;
+                self.volume = val & 0b00001111;
+            },
+            2 => self.timer_period = (self.timer_period & 0x0700) | val as u16,
+            3 =>
+            {
+                self.timer_period = (self.timer_period & 0x00FF) | (((val & 0b111) as u16) << 8);
+                if self.enabled
+                {
+                    self.length = LENGTH_TABLE[(val >> 3) as usize];
+                }
+                self.sequence = 0;
+                self.envelope_start = true;
+            },
+            _ => (), // no sweep unit behind +1
+        }
+    }
+
+    fn set_enabled(&mut self, enabled: bool)
+    {
+        self.enabled = enabled;
+        if !enabled
+        {
+            self.length = 0;
+        }
+    }
+
+    fn clock_timer(&mut self)
+    {
+        if self.timer == 0
+        {
+            self.timer = self.timer_period;
+            self.sequence = (self.sequence + 1) & 0b111;
+        }
+        else
+        {
+            self.timer -= 1;
+        }
+    }
+
+    fn clock_envelope(&mut self)
+    {
+        if self.envelope_start
+        {
+            self.envelope_start = false;
+            self.envelope_decay = 15;
+            self.envelope_divider = self.volume;
+        }
+        else if self.envelope_divider == 0
+        {
+            self.envelope_divider = self.volume;
+            if self.envelope_decay > 0
+            {
+                self.envelope_decay -= 1;
+            }
+            else if self.halt
+            {
+                self.envelope_decay = 15;
+            }
+        }
+        else
+        {
+            self.envelope_divider -= 1;
+        }
+    }
+
+    fn clock_length(&mut self)
+    {
+        if self.length > 0 && !self.halt
+        {
+            self.length -= 1;
+        }
+    }
+
+    // unlike the APU pulses, short timer periods are not silenced
+    fn output(&self) -> u8
+    {
+        if self.length == 0 || DUTY_SEQUENCES[self.duty as usize][self.sequence as usize] == 0
+        {
+            return 0;
+        }
+        return if self.constant_volume { self.volume } else { self.envelope_decay };
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Mmc5Audio
+{
+    pulses: [Pulse; 2],
+    pcm_read_mode: bool,
+    pcm: u8,
+    frame_divider: u16,
+    odd_cycle: bool,
+}
+
+pub fn init_mmc5_audio() -> Mmc5Audio
+{
+    let audio = Mmc5Audio
+    {
+        pulses: [init_pulse(), init_pulse()],
+        pcm_read_mode: false,
+        pcm: 0,
+        frame_divider: 0,
+        odd_cycle: false,
+    };
+    return audio;
+}
+
+impl Mmc5Audio
+{
+    pub fn write(&mut self, address: u16, val: u8)
+    {
+        match address
+        {
+            0x5000..=0x5003 => self.pulses[0].write(address - 0x5000, val),
+            0x5004..=0x5007 => self.pulses[1].write(address - 0x5004, val),
+            0x5010 => self.pcm_read_mode = (val & 0b1) != 0,
+            // writes of 0 are ignored; read mode, which samples CPU reads of
+            // 0x8000 - 0xBFFF instead, is not emulated
+            0x5011 if !self.pcm_read_mode && val != 0 => self.pcm = val,
+            0x5015 =>
+            {
+                self.pulses[0].set_enabled((val & 0b01) != 0);
+                self.pulses[1].set_enabled((val & 0b10) != 0);
+            },
+            _ => (),
+        }
+    }
+
+    // 0x5015
+    pub fn read_status(&self) -> u8
+    {
+        return (self.pulses[0].length > 0) as u8 | (((self.pulses[1].length > 0) as u8) << 1);
+    }
+
+    // one CPU cycle, the pulse timers tick every other cycle like the APU's
+    pub fn clock(&mut self)
+    {
+        if self.odd_cycle
+        {
+            self.pulses[0].clock_timer();
+            self.pulses[1].clock_timer();
+        }
+        self.odd_cycle = !self.odd_cycle;
+
+        self.frame_divider += 1;
+        if self.frame_divider >= FRAME_PERIOD
+        {
+            self.frame_divider = 0;
+            for pulse in self.pulses.iter_mut()
+            {
+                pulse.clock_envelope();
+                pulse.clock_length();
+            }
+        }
+    }
+
+    // the pulses go through the same nonlinear DAC as the APU pulses and the
+    // PCM level like the DMC does, on the APU's 0.0 - 1.0 output scale
+    pub fn output(&self) -> f32
+    {
+        let pulses = (self.pulses[0].output() + self.pulses[1].output()) as f32;
+        let pulse_out = if pulses == 0.0 { 0.0 } else { 95.88 / (8128.0 / pulses + 100.0) };
+        let pcm = (self.pcm >> 1) as f32;
+        let pcm_out = if pcm == 0.0 { 0.0 } else { 159.79 / (1.0 / (pcm / 22638.0) + 100.0) };
+        return pulse_out + pcm_out;
+    }
+}
@@ -0,0 +1,61 @@
+use crate::header::*;
+use crate::mapper::{Mapped, Mapper};
+
+// NROM, mapper 0: no banking at all.
+// NROM-128 carries 16KB of PRG-ROM which shows up twice, NROM-256 fills
+// 0x8000 - 0xFFFF with 32KB. Family Basic adds PRG-RAM at 0x6000.
+#[derive(Debug, Clone)]
+pub struct Nrom
+{
+    mirroring: Mirroring, // soldered on the board
+}
+
+pub fn init_nrom(h: &Header) -> Nrom
+{
+    let mapper = Nrom
+    {
+        mirroring: h.get_mirroring(),
+    };
+    return mapper;
+}
+
+impl Mapper for Nrom
+{
+    fn cpu_read(&mut self, address: u16) -> Mapped
+    {
+        return match address
+        {
+            0x6000..=0x7FFF => Mapped::PrgRam((address - 0x6000) as usize),
+            0x8000..=0xFFFF => Mapped::PrgRom((address - 0x8000) as usize), // NROM-128 wraps onto itself
+            _               => Mapped::Nothing,
+        };
+    }
+
+    fn cpu_write(&mut self, address: u16, _val: u8) -> Mapped
+    {
+        return match address
+        {
+            0x6000..=0x7FFF => Mapped::PrgRam((address - 0x6000) as usize),
+            _               => Mapped::Nothing,
+        };
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Mapped
+    {
+        return match address
+        {
+            0x0000..=0x1FFF => Mapped::Chr(address as usize),
+            _               => Mapped::Nothing,
+        };
+    }
+
+    fn ppu_write(&mut self, address: u16, _val: u8) -> Mapped
+    {
+        return self.ppu_read(address);
+    }
+
+    fn get_mirroring(&self) -> Mirroring
+    {
+        return self.mirroring;
+    }
+}
@@ -0,0 +1,72 @@
+use crate::header::*;
+use crate::mapper::{bus_conflicts, Mapped, Mapper};
+
+// UxROM, mapper 2 (UNROM, UOROM).
+// Any write to 0x8000 - 0xFFFF picks the 16KB bank at 0x8000,
+// the last bank is fixed at 0xC000. CHR is 8KB of RAM.
+#[derive(Debug, Clone)]
+pub struct Uxrom
+{
+    prg_bank: u8,
+    last_bank: usize,
+    mirroring: Mirroring, // soldered on the board
+    bus_conflicts: bool,
+}
+
+pub fn init_uxrom(h: &Header) -> Uxrom
+{
+    let mapper = Uxrom
+    {
+        prg_bank: 0,
+        last_bank: (h.get_prg_rom_size() / 0x4000).saturating_sub(1),
+        mirroring: h.get_mirroring(),
+        bus_conflicts: bus_conflicts(h, true),
+    };
+    return mapper;
+}
+
+impl Mapper for Uxrom
+{
+    fn cpu_read(&mut self, address: u16) -> Mapped
+    {
+        return match address
+        {
+            0x8000..=0xBFFF => Mapped::PrgRom(self.prg_bank as usize * 0x4000 + (address & 0x3FFF) as usize),
+            0xC000..=0xFFFF => Mapped::PrgRom(self.last_bank * 0x4000 + (address & 0x3FFF) as usize),
+            _               => Mapped::Nothing,
+        };
+    }
+
+    fn cpu_write(&mut self, address: u16, val: u8) -> Mapped
+    {
+        if address >= 0x8000
+        {
+            self.prg_bank = val;
+        }
+        return Mapped::Nothing;
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Mapped
+    {
+        return match address
+        {
+            0x0000..=0x1FFF => Mapped::Chr(address as usize),
+            _               => Mapped::Nothing,
+        };
+    }
+
+    fn ppu_write(&mut self, address: u16, _val: u8) -> Mapped
+    {
+        return self.ppu_read(address);
+    }
+
+    fn get_mirroring(&self) -> Mirroring
+    {
+        return self.mirroring;
+    }
+
+    fn has_bus_conflicts(&self) -> bool
+    {
+        return self.bus_conflicts;
+    }
+}
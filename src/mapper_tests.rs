@@ -0,0 +1,788 @@
+use crate::bus::*;
+use crate::cartridge::*;
+use crate::cpu::*;
+use crate::file_handling::*;
+use crate::header::*;
+use crate::mapper::*;
+
+// an iNES image whose every PRG and CHR byte holds the number of its 1KB page
+fn image(mapper: u8, prg_banks: u8, chr_banks: u8, flags_6: u8) -> Vec<u8>
+{
+    let mut buffer = vec![0x4E, 0x45, 0x53, 0x1A, prg_banks, chr_banks, (mapper << 4) | flags_6, mapper & 0xF0];
+    buffer.resize(0x10, 0x0);
+    let trainer = if (flags_6 & 0b00000100) != 0 { 0x200 } else { 0x0 };
+    buffer.extend((0..trainer).map(|i| i as u8));
+    buffer.extend((0..prg_banks as usize * 0x4000).map(|i| (i / 0x400) as u8));
+    buffer.extend((0..chr_banks as usize * 0x2000).map(|i| (i / 0x400) as u8));
+    return buffer;
+}
+
+fn cartridge(buffer: Vec<u8>) -> Cartridge
+{
+    return init_cartridge(parse_rom(&buffer).unwrap()).unwrap();
+}
+
+#[test]
+fn nrom_128_is_mirrored()
+{
+    let mut cart = cartridge(image(0, 1, 1, 0x01));
+    assert_eq!(cart.cpu_read(0x8000), 0);
+    assert_eq!(cart.cpu_read(0xBFFF), 15);
+    assert_eq!(cart.cpu_read(0xC000), 0);
+    assert_eq!(cart.cpu_read(0xFFFF), 15);
+    assert_eq!(cart.get_mirroring(), Mirroring::Vertical);
+}
+
+#[test]
+fn nrom_256_fills_the_window()
+{
+    let mut cart = cartridge(image(0, 2, 1, 0x00));
+    assert_eq!(cart.cpu_read(0xC000), 16);
+    assert_eq!(cart.cpu_read(0xFFFF), 31);
+    assert_eq!(cart.get_mirroring(), Mirroring::Horizontal);
+
+    // ROM ignores writes
+    cart.cpu_write(0x8000, 0x55);
+    assert_eq!(cart.cpu_read(0x8000), 0);
+}
+
+#[test]
+fn nrom_prg_ram_and_trainer()
+{
+    let mut cart = cartridge(image(0, 1, 1, 0x04));
+    assert_eq!(cart.cpu_read(0x7000), 0x00);
+    assert_eq!(cart.cpu_read(0x71FF), 0xFF);
+
+    cart.cpu_write(0x6000, 0x42);
+    assert_eq!(cart.cpu_read(0x6000), 0x42);
+}
+
+#[test]
+fn chr_ram_is_writable_and_chr_rom_is_not()
+{
+    let mut cart = cartridge(image(0, 1, 1, 0x00));
+    assert_eq!(cart.ppu_read(0x1C00), 7);
+    cart.ppu_write(0x1C00, 0x42);
+    assert_eq!(cart.ppu_read(0x1C00), 7);
+
+    let mut cart = cartridge(image(0, 1, 0, 0x00));
+    assert!(cart.has_chr_ram());
+    cart.ppu_write(0x1C00, 0x42);
+    assert_eq!(cart.ppu_read(0x1C00), 0x42);
+}
+
+// MMC1 registers take five serial writes, least significant bit first
+fn mmc1_write(cart: &mut Cartridge, address: u16, val: u8)
+{
+    for i in 0..5
+    {
+        cart.cpu_write(address, (val >> i) & 0b1);
+    }
+}
+
+#[test]
+fn mmc1_prg_banking_modes()
+{
+    let mut cart = cartridge(image(1, 8, 1, 0x00));
+    // power up fixes the last bank at 0xC000
+    assert_eq!(cart.cpu_read(0x8000), 0);
+    assert_eq!(cart.cpu_read(0xC000), 7 * 16);
+
+    mmc1_write(&mut cart, 0xE000, 3);
+    assert_eq!(cart.cpu_read(0x8000), 3 * 16);
+    assert_eq!(cart.cpu_read(0xC000), 7 * 16);
+
+    // fix the first bank, switch 0xC000
+    mmc1_write(&mut cart, 0x8000, 0b01010);
+    assert_eq!(cart.cpu_read(0x8000), 0);
+    assert_eq!(cart.cpu_read(0xC000), 3 * 16);
+    assert_eq!(cart.get_mirroring(), Mirroring::Vertical);
+
+    // 32KB mode drops the low bank bit
+    mmc1_write(&mut cart, 0x8000, 0b00011);
+    assert_eq!(cart.cpu_read(0x8000), 2 * 16);
+    assert_eq!(cart.cpu_read(0xC000), 3 * 16);
+    assert_eq!(cart.get_mirroring(), Mirroring::Horizontal);
+}
+
+#[test]
+fn mmc1_reset_write_clears_the_shift_register()
+{
+    let mut cart = cartridge(image(1, 8, 1, 0x00));
+    mmc1_write(&mut cart, 0x8000, 0b00000); // 32KB mode, single screen
+    assert_eq!(cart.get_mirroring(), Mirroring::SingleScreenLower);
+
+    // two stray bits, then a reset, then a full PRG bank load
+    cart.cpu_write(0xE000, 1);
+    cart.cpu_write(0xE000, 1);
+    cart.cpu_write(0x8000, 0x80);
+    mmc1_write(&mut cart, 0xE000, 2);
+    assert_eq!(cart.cpu_read(0x8000), 2 * 16);
+    assert_eq!(cart.cpu_read(0xC000), 7 * 16);
+}
+
+#[test]
+fn mmc1_chr_banking_modes()
+{
+    let mut cart = cartridge(image(1, 2, 4, 0x00));
+    // 8KB mode drops the low bank bit
+    mmc1_write(&mut cart, 0xA000, 3);
+    assert_eq!(cart.ppu_read(0x0000), 8);
+    assert_eq!(cart.ppu_read(0x1000), 12);
+
+    mmc1_write(&mut cart, 0x8000, 0b11100);
+    mmc1_write(&mut cart, 0xC000, 5);
+    assert_eq!(cart.ppu_read(0x0000), 12);
+    assert_eq!(cart.ppu_read(0x1000), 20);
+}
+
+#[test]
+fn mmc1_prg_ram_enable()
+{
+    let mut cart = cartridge(image(1, 2, 1, 0x00));
+    cart.cpu_write(0x6000, 0x42);
+    assert_eq!(cart.cpu_read(0x6000), 0x42);
+
+    mmc1_write(&mut cart, 0xE000, 0b10000);
+    assert_eq!(cart.cpu_read(0x6000), 0x00);
+    cart.cpu_write(0x6000, 0x17);
+
+    mmc1_write(&mut cart, 0xE000, 0b00000);
+    assert_eq!(cart.cpu_read(0x6000), 0x42);
+}
+
+#[test]
+fn mmc1_sxrom_outer_bank_and_prg_ram_pages()
+{
+    let mut buffer = image(1, 32, 0, 0x00);
+    buffer[0x8] = 4; // 32KB PRG-RAM
+    buffer[0x10 + 0x40000] = 0xAB; // first bank of the upper 256KB
+    buffer[0x10 + 0x7C000] = 0xCD; // last bank of the upper 256KB
+    let mut cart = cartridge(buffer);
+
+    mmc1_write(&mut cart, 0xA000, 0b10000);
+    assert_eq!(cart.cpu_read(0x8000), 0xAB);
+    assert_eq!(cart.cpu_read(0xC000), 0xCD);
+
+    mmc1_write(&mut cart, 0xA000, 0b01000);
+    cart.cpu_write(0x6000, 0x11);
+    mmc1_write(&mut cart, 0xA000, 0b00000);
+    assert_eq!(cart.cpu_read(0x6000), 0x00);
+    mmc1_write(&mut cart, 0xA000, 0b01000);
+    assert_eq!(cart.cpu_read(0x6000), 0x11);
+}
+
+#[test]
+fn mmc1_4k_chr_mode_follows_ppu_a12()
+{
+    let mut buffer = image(1, 32, 0, 0x00);
+    buffer[0x8] = 4; // 32KB PRG-RAM
+    buffer[0x10 + 0x3C000] = 0xAB; // last bank of the lower 256KB
+    buffer[0x10 + 0x7C000] = 0xCD; // last bank of the upper 256KB
+    let mut cart = cartridge(buffer);
+
+    mmc1_write(&mut cart, 0x8000, 0b11100);
+    mmc1_write(&mut cart, 0xA000, 0b00100); // lower half, RAM page 1
+    mmc1_write(&mut cart, 0xC000, 0b11000); // upper half, RAM page 2
+    cart.ppu_read(0x0000);
+    assert_eq!(cart.cpu_read(0xC000), 0xAB);
+    cart.cpu_write(0x6000, 0x11);
+
+    cart.ppu_read(0x1000);
+    assert_eq!(cart.cpu_read(0xC000), 0xCD);
+    assert_eq!(cart.cpu_read(0x6000), 0x00);
+    cart.cpu_write(0x6000, 0x22);
+
+    // nametable fetches have A12 low
+    cart.ppu_read(0x2000);
+    assert_eq!(cart.cpu_read(0xC000), 0xAB);
+    assert_eq!(cart.cpu_read(0x6000), 0x11);
+    cart.ppu_read(0x1FFF);
+    assert_eq!(cart.cpu_read(0x6000), 0x22);
+
+    // 8KB mode always uses the first register
+    mmc1_write(&mut cart, 0x8000, 0b01100);
+    cart.ppu_read(0x1000);
+    assert_eq!(cart.cpu_read(0xC000), 0xAB);
+}
+
+// turn an image into NES 2.0 with the given submapper and 8KB of CHR-RAM
+fn nes2(mut buffer: Vec<u8>, submapper: u8) -> Vec<u8>
+{
+    buffer[0x7] |= 0b00001000;
+    buffer[0x8] = submapper << 4;
+    buffer[0xB] = 0x07;
+    return buffer;
+}
+
+#[test]
+fn uxrom_switches_the_low_bank_with_bus_conflicts()
+{
+    let mut buffer = image(2, 8, 0, 0x00);
+    buffer[0x10 + 0x1FFFF] = 0xFF; // the byte at 0xFFFF lets any value through
+    let mut cart = cartridge(buffer.clone());
+    assert_eq!(cart.cpu_read(0xC000), 7 * 16);
+
+    cart.cpu_write(0xFFFF, 3);
+    assert_eq!(cart.cpu_read(0x8000), 3 * 16);
+    assert_eq!(cart.cpu_read(0xC000), 7 * 16);
+
+    // ROM drives 0x30 at 0x8000, which masks the 5 away
+    cart.cpu_write(0x8000, 5);
+    assert_eq!(cart.cpu_read(0x8000), 0);
+
+    // submapper 1 has no bus conflicts
+    let mut cart = cartridge(nes2(buffer, 1));
+    cart.cpu_write(0xFFFF, 3);
+    cart.cpu_write(0x8000, 5);
+    assert_eq!(cart.cpu_read(0x8000), 5 * 16);
+
+    // CHR-RAM
+    cart.ppu_write(0x0123, 0x42);
+    assert_eq!(cart.ppu_read(0x0123), 0x42);
+}
+
+#[test]
+fn cnrom_switches_chr()
+{
+    let mut buffer = image(3, 2, 4, 0x00);
+    buffer[0x10 + 0x7FFF] = 0xFF;
+    let mut cart = cartridge(buffer);
+    assert_eq!(cart.cpu_read(0x8000), 0);
+    assert_eq!(cart.cpu_read(0xC000), 16);
+    assert_eq!(cart.ppu_read(0x0000), 0);
+
+    cart.cpu_write(0xFFFF, 2);
+    assert_eq!(cart.ppu_read(0x0000), 16);
+    assert_eq!(cart.ppu_read(0x1FFF), 23);
+
+    // CHR-ROM stays read only
+    cart.ppu_write(0x0000, 0x42);
+    assert_eq!(cart.ppu_read(0x0000), 16);
+}
+
+#[test]
+fn axrom_switches_32k_and_single_screen()
+{
+    let buffer = image(7, 8, 0, 0x00);
+    let mut cart = cartridge(buffer.clone());
+    assert_eq!(cart.get_mirroring(), Mirroring::SingleScreenLower);
+
+    cart.cpu_write(0x8000, 0x12);
+    assert_eq!(cart.cpu_read(0x8000), 64);
+    assert_eq!(cart.cpu_read(0xFFFF), 95);
+    assert_eq!(cart.get_mirroring(), Mirroring::SingleScreenUpper);
+
+    cart.cpu_write(0x8000, 0x01);
+    assert_eq!(cart.cpu_read(0x8000), 32);
+    assert_eq!(cart.get_mirroring(), Mirroring::SingleScreenLower);
+
+    // submapper 2 boards have bus conflicts, ROM drives 0x00 here
+    let mut cart = cartridge(nes2(buffer, 2));
+    cart.cpu_write(0x8000, 0x12);
+    assert_eq!(cart.cpu_read(0x8000), 0);
+}
+
+fn mmc3_write(cart: &mut Cartridge, register: u8, val: u8)
+{
+    cart.cpu_write(0x8000, register);
+    cart.cpu_write(0x8001, val);
+}
+
+// what the PPU puts on its bus over one scanline with the background at
+// 0x0000 and sprites at 0x1000: a long A12 low stretch, then sprite
+// fetches with two nametable reads between each pair of pattern reads
+fn mmc3_scanline(cart: &mut Cartridge)
+{
+    for _ in 0..8
+    {
+        cart.ppu_read(0x2000);
+        cart.ppu_read(0x0000);
+    }
+    for _ in 0..8
+    {
+        cart.ppu_read(0x2000);
+        cart.ppu_read(0x2000);
+        cart.ppu_read(0x1000);
+        cart.ppu_read(0x1008);
+    }
+}
+
+#[test]
+fn mmc3_prg_banking_modes()
+{
+    let mut cart = cartridge(image(4, 4, 2, 0x00));
+    assert_eq!(cart.cpu_read(0x8000), 0);
+    assert_eq!(cart.cpu_read(0xA000), 8);
+    assert_eq!(cart.cpu_read(0xC000), 48);
+    assert_eq!(cart.cpu_read(0xE000), 56);
+
+    mmc3_write(&mut cart, 6, 3);
+    mmc3_write(&mut cart, 7, 5);
+    assert_eq!(cart.cpu_read(0x8000), 24);
+    assert_eq!(cart.cpu_read(0xA000), 40);
+
+    // PRG mode 1 swaps 0x8000 and 0xC000, the second last bank moves down
+    cart.cpu_write(0x8000, 0b01000000);
+    assert_eq!(cart.cpu_read(0x8000), 48);
+    assert_eq!(cart.cpu_read(0xA000), 40);
+    assert_eq!(cart.cpu_read(0xC000), 24);
+    assert_eq!(cart.cpu_read(0xE000), 56);
+
+    // registers are mirrored by even and odd addresses
+    cart.cpu_write(0x9FFE, 6);
+    cart.cpu_write(0x9FFF, 2);
+    assert_eq!(cart.cpu_read(0x8000), 16);
+}
+
+#[test]
+fn mmc3_chr_banking_and_inversion()
+{
+    let mut cart = cartridge(image(4, 4, 2, 0x00));
+    mmc3_write(&mut cart, 0, 5); // the low bit of a 2KB bank is ignored
+    mmc3_write(&mut cart, 1, 6);
+    mmc3_write(&mut cart, 2, 9);
+    mmc3_write(&mut cart, 5, 15);
+    assert_eq!(cart.ppu_read(0x0000), 4);
+    assert_eq!(cart.ppu_read(0x0400), 5);
+    assert_eq!(cart.ppu_read(0x0800), 6);
+    assert_eq!(cart.ppu_read(0x0C00), 7);
+    assert_eq!(cart.ppu_read(0x1000), 9);
+    assert_eq!(cart.ppu_read(0x1C00), 15);
+
+    // inversion puts the 2KB banks at 0x1000 and the 1KB banks at 0x0000
+    cart.cpu_write(0x8000, 0b10000000);
+    assert_eq!(cart.ppu_read(0x0000), 9);
+    assert_eq!(cart.ppu_read(0x0C00), 15);
+    assert_eq!(cart.ppu_read(0x1000), 4);
+    assert_eq!(cart.ppu_read(0x1C00), 7);
+}
+
+#[test]
+fn mmc3_mirroring_and_prg_ram_protect()
+{
+    let mut cart = cartridge(image(4, 4, 2, 0x00));
+    cart.cpu_write(0xA000, 1);
+    assert_eq!(cart.get_mirroring(), Mirroring::Horizontal);
+    cart.cpu_write(0xA000, 0);
+    assert_eq!(cart.get_mirroring(), Mirroring::Vertical);
+
+    cart.cpu_write(0x6000, 0x42);
+    assert_eq!(cart.cpu_read(0x6000), 0x42);
+
+    // write protected
+    cart.cpu_write(0xA001, 0b11000000);
+    cart.cpu_write(0x6000, 0x13);
+    assert_eq!(cart.cpu_read(0x6000), 0x42);
+
+    // chip disabled, reads are open bus
+    cart.cpu_write(0xA001, 0b00000000);
+    assert_eq!(cart.cpu_read(0x6000), 0x0);
+    cart.cpu_write(0xA001, 0b10000000);
+    assert_eq!(cart.cpu_read(0x6000), 0x42);
+
+    // four screen boards ignore the mirroring register
+    let mut cart = cartridge(image(4, 4, 2, 0x08));
+    cart.cpu_write(0xA000, 1);
+    assert_eq!(cart.get_mirroring(), Mirroring::FourScreen);
+}
+
+#[test]
+fn mmc3_scanline_irq()
+{
+    let mut cart = cartridge(image(4, 4, 2, 0x00));
+    cart.cpu_write(0xC000, 2);
+    cart.cpu_write(0xC001, 0);
+    cart.cpu_write(0xE001, 0);
+
+    // reload to 2, then 1, then 0 fires
+    mmc3_scanline(&mut cart);
+    mmc3_scanline(&mut cart);
+    assert!(!cart.irq_pending());
+    mmc3_scanline(&mut cart);
+    assert!(cart.irq_pending());
+
+    // the line stays asserted until 0xE000 acknowledges it, the counter reloads to 2
+    mmc3_scanline(&mut cart);
+    assert!(cart.irq_pending());
+    cart.cpu_write(0xE000, 0);
+    assert!(!cart.irq_pending());
+
+    // disabled counters keep counting down to 0 without firing
+    mmc3_scanline(&mut cart);
+    mmc3_scanline(&mut cart);
+    assert!(!cart.irq_pending());
+    cart.cpu_write(0xE001, 0);
+    mmc3_scanline(&mut cart);
+    mmc3_scanline(&mut cart);
+    assert!(!cart.irq_pending());
+    mmc3_scanline(&mut cart);
+    assert!(cart.irq_pending());
+}
+
+#[test]
+fn mmc3_zero_latch_rev_a_and_rev_b()
+{
+    let buffer = image(4, 4, 2, 0x00);
+
+    // Rev B fires on every scanline with a latch of 0
+    let mut cart = cartridge(buffer.clone());
+    cart.cpu_write(0xC000, 0);
+    cart.cpu_write(0xC001, 0);
+    cart.cpu_write(0xE001, 0);
+    for _ in 0..3
+    {
+        mmc3_scanline(&mut cart);
+        assert!(cart.irq_pending());
+        cart.cpu_write(0xE000, 0);
+        cart.cpu_write(0xE001, 0);
+    }
+
+    // Rev A (NES 2.0 submapper 4) only fires on the reload
+    let mut cart = cartridge(nes2(buffer, 4));
+    cart.cpu_write(0xC000, 0);
+    cart.cpu_write(0xC001, 0);
+    cart.cpu_write(0xE001, 0);
+    mmc3_scanline(&mut cart);
+    assert!(cart.irq_pending());
+    cart.cpu_write(0xE000, 0);
+    cart.cpu_write(0xE001, 0);
+    mmc3_scanline(&mut cart);
+    mmc3_scanline(&mut cart);
+    assert!(!cart.irq_pending());
+}
+
+#[test]
+fn mmc3_irq_reaches_the_cpu()
+{
+    let mut bus = init_nes_bus();
+    bus.insert_cartridge(cartridge(image(4, 4, 2, 0x00)));
+    let mut cpu = init_cpu(bus);
+    for i in 0..4
+    {
+        cpu.get_bus_mut().write(0x0600 + i, 0xEA);
+    }
+    cpu.set_pc(0x0600);
+    cpu.set_p(0x20);
+
+    let cart = cpu.get_bus_mut().get_cartridge_mut().unwrap();
+    cart.cpu_write(0xC000, 0);
+    cart.cpu_write(0xC001, 0);
+    cart.cpu_write(0xE001, 0);
+    mmc3_scanline(cart);
+    assert!(cpu.get_bus().irq_line());
+
+    // the vector comes from the fixed last bank, whose bytes are all 63
+    cpu.step();
+    assert_eq!(cpu.get_pc(), 0x3F3F);
+}
+
+// the background fetches for columns 0 and 1 of a scanline, made at the end
+// of the scanline before, followed by the two dummy nametable reads whose
+// address the next scanline starts with
+fn mmc5_prefetch(cart: &mut Cartridge, scanline: u16)
+{
+    for column in 0..2
+    {
+        mmc5_tile(cart, scanline, column);
+    }
+    cart.ppu_read(0x2000 + (scanline / 8) * 32 + 2);
+    cart.ppu_read(0x2000 + (scanline / 8) * 32 + 2);
+}
+
+// nametable, attribute and both pattern bytes of one background tile
+fn mmc5_tile(cart: &mut Cartridge, scanline: u16, column: u16) -> [u8; 4]
+{
+    let tile = cart.ppu_read(0x2000 + (scanline / 8) * 32 + (column & 31));
+    let attribute = cart.ppu_read(0x23C0 + (scanline / 32) * 8 + (column & 31) / 4);
+    let pattern = tile as u16 * 16 + scanline % 8;
+    return [tile, attribute, cart.ppu_read(pattern), cart.ppu_read(pattern + 8)];
+}
+
+// one rendered scanline with 8x8 sprites at 0x1000, returns the fetches for columns 2 - 33
+fn mmc5_scanline(cart: &mut Cartridge, scanline: u16) -> Vec<[u8; 4]>
+{
+    let tiles = (2..34).map(|column| mmc5_tile(cart, scanline, column)).collect();
+    for _ in 0..8
+    {
+        cart.ppu_read(0x2000);
+        cart.ppu_read(0x2000);
+        cart.ppu_read(0x1000);
+        cart.ppu_read(0x1008);
+    }
+    mmc5_prefetch(cart, scanline + 1);
+    return tiles;
+}
+
+#[test]
+fn mmc5_prg_banking_modes()
+{
+    let mut buffer = nes2(image(5, 8, 1, 0x00), 0);
+    buffer[0xA] = 0x08; // 16KB PRG-RAM
+    let mut cart = cartridge(buffer);
+    assert_eq!(cart.cpu_read(0xE000), 120);
+
+    cart.cpu_write(0x5114, 0x81);
+    cart.cpu_write(0x5115, 0x82);
+    cart.cpu_write(0x5116, 0x83);
+    assert_eq!(cart.cpu_read(0x8000), 8);
+    assert_eq!(cart.cpu_read(0xA000), 16);
+    assert_eq!(cart.cpu_read(0xC000), 24);
+
+    cart.cpu_write(0x5100, 0);
+    cart.cpu_write(0x5117, 0x85); // low two bits ignored
+    assert_eq!(cart.cpu_read(0x8000), 32);
+    assert_eq!(cart.cpu_read(0xFFFF), 63);
+
+    cart.cpu_write(0x5100, 1);
+    cart.cpu_write(0x5115, 0x83);
+    assert_eq!(cart.cpu_read(0x8000), 16);
+    assert_eq!(cart.cpu_read(0xC000), 32);
+
+    cart.cpu_write(0x5100, 2);
+    cart.cpu_write(0x5116, 0x87);
+    assert_eq!(cart.cpu_read(0xA000), 24);
+    assert_eq!(cart.cpu_read(0xC000), 56);
+    assert_eq!(cart.cpu_read(0xE000), 40);
+
+    // PRG-RAM can be banked into 0x8000 - 0xDFFF once both protect registers allow writes
+    cart.cpu_write(0x5100, 3);
+    cart.cpu_write(0x5114, 0x01);
+    cart.cpu_write(0x8000, 0x42);
+    assert_eq!(cart.cpu_read(0x8000), 0x0);
+    cart.cpu_write(0x5102, 0b10);
+    cart.cpu_write(0x5103, 0b01);
+    cart.cpu_write(0x8000, 0x42);
+    cart.cpu_write(0x5113, 0x01);
+    assert_eq!(cart.cpu_read(0x6000), 0x42);
+}
+
+#[test]
+fn mmc5_chr_banking_modes()
+{
+    let mut cart = cartridge(image(5, 2, 8, 0x00));
+    cart.cpu_write(0x5120, 5);
+    cart.cpu_write(0x5127, 9);
+    assert_eq!(cart.ppu_read(0x0000), 5);
+    assert_eq!(cart.ppu_read(0x1C00), 9);
+
+    cart.cpu_write(0x5101, 0);
+    cart.cpu_write(0x5127, 2);
+    assert_eq!(cart.ppu_read(0x0000), 16);
+    assert_eq!(cart.ppu_read(0x1FFF), 23);
+
+    cart.cpu_write(0x5101, 1);
+    cart.cpu_write(0x5123, 3);
+    assert_eq!(cart.ppu_read(0x0000), 12);
+    assert_eq!(cart.ppu_read(0x1000), 8);
+
+    cart.cpu_write(0x5101, 2);
+    cart.cpu_write(0x5121, 5);
+    assert_eq!(cart.ppu_read(0x0000), 10);
+
+    // with 8x16 sprites the background set is used when it was written last
+    cart.cpu_write(0x5101, 3);
+    cart.cpu_write(0x2000, 0b00100000);
+    cart.cpu_write(0x5128, 30);
+    assert_eq!(cart.ppu_read(0x0000), 30);
+    assert_eq!(cart.ppu_read(0x1000), 30);
+    cart.cpu_write(0x5120, 4);
+    assert_eq!(cart.ppu_read(0x0000), 4);
+}
+
+#[test]
+fn mmc5_nametables_exram_and_fill()
+{
+    let buffer = image(5, 2, 1, 0x00);
+    let mut mapper = create_mapper(parse_rom(&buffer).unwrap().get_header()).unwrap();
+    mapper.cpu_write(0x5105, 0b11100100);
+    assert_eq!(mapper.ppu_read(0x2005), Mapped::Ciram(0x005));
+    assert_eq!(mapper.ppu_read(0x2405), Mapped::Ciram(0x405));
+    assert_eq!(mapper.get_mirroring(), Mirroring::FourScreen);
+    mapper.cpu_write(0x5105, 0x44);
+    assert_eq!(mapper.get_mirroring(), Mirroring::Vertical);
+
+    let mut cart = cartridge(buffer);
+    cart.cpu_write(0x5105, 0b11100100);
+
+    // CPU RAM mode
+    cart.cpu_write(0x5104, 2);
+    cart.cpu_write(0x5C05, 0x77);
+    assert_eq!(cart.cpu_read(0x5C05), 0x77);
+    assert_eq!(cart.ppu_read(0x2805), 0x0);
+
+    // read only
+    cart.cpu_write(0x5104, 3);
+    cart.cpu_write(0x5C05, 0x11);
+    assert_eq!(cart.cpu_read(0x5C05), 0x77);
+
+    // as a nametable the CPU writes 0 outside rendering, the PPU can write it
+    cart.cpu_write(0x5104, 0);
+    assert_eq!(cart.ppu_read(0x2805), 0x77);
+    cart.cpu_write(0x5C05, 0x11);
+    assert_eq!(cart.ppu_read(0x2805), 0x0);
+    cart.ppu_write(0x2805, 0x33);
+    assert_eq!(cart.ppu_read(0x2805), 0x33);
+
+    cart.cpu_write(0x5106, 0x21);
+    cart.cpu_write(0x5107, 2);
+    assert_eq!(cart.ppu_read(0x2C00), 0x21);
+    assert_eq!(cart.ppu_read(0x2FC0), 0xAA);
+}
+
+#[test]
+fn mmc5_multiplier()
+{
+    let mut cart = cartridge(image(5, 2, 1, 0x00));
+    assert_eq!(cart.cpu_read(0x5205), 0x01);
+    assert_eq!(cart.cpu_read(0x5206), 0xFE);
+    cart.cpu_write(0x5205, 200);
+    cart.cpu_write(0x5206, 150);
+    assert_eq!(cart.cpu_read(0x5205), 0x30);
+    assert_eq!(cart.cpu_read(0x5206), 0x75);
+}
+
+#[test]
+fn mmc5_scanline_irq()
+{
+    let mut cart = cartridge(image(5, 2, 1, 0x00));
+    cart.cpu_write(0x5203, 3);
+    cart.cpu_write(0x5204, 0b10000000);
+    assert_eq!(cart.cpu_read(0x5204), 0x00);
+
+    mmc5_prefetch(&mut cart, 0);
+    for scanline in 0..3
+    {
+        mmc5_scanline(&mut cart, scanline);
+    }
+    assert_eq!(cart.cpu_read(0x5204), 0x40);
+    assert!(!cart.irq_pending());
+    mmc5_scanline(&mut cart, 3);
+    assert!(cart.irq_pending());
+
+    // reading the status acknowledges
+    assert_eq!(cart.cpu_peek(0x5204), 0xC0);
+    assert_eq!(cart.cpu_read(0x5204), 0xC0);
+    assert_eq!(cart.cpu_read(0x5204), 0x40);
+    assert!(!cart.irq_pending());
+
+    // the NMI vector fetch ends the frame
+    cart.cpu_read(0xFFFA);
+    assert_eq!(cart.cpu_read(0x5204), 0x00);
+
+    // pending is still flagged with the IRQ disabled, the line is not driven
+    cart.cpu_write(0x5204, 0);
+    mmc5_prefetch(&mut cart, 0);
+    for scanline in 0..4
+    {
+        mmc5_scanline(&mut cart, scanline);
+    }
+    assert_eq!(cart.cpu_read(0x5204), 0xC0);
+    assert!(!cart.irq_pending());
+}
+
+#[test]
+fn mmc5_extended_attributes()
+{
+    let mut cart = cartridge(image(5, 2, 8, 0x00));
+    cart.cpu_write(0x5104, 2);
+    cart.cpu_write(0x5C02, 0b11000101); // palette 3, 4KB CHR bank 5
+    cart.cpu_write(0x5C03, 0b01000010); // palette 1, 4KB CHR bank 2
+    cart.cpu_write(0x5104, 1);
+
+    mmc5_prefetch(&mut cart, 0);
+    let tiles = mmc5_scanline(&mut cart, 0);
+    assert_eq!(tiles[0], [0x0, 0xFF, 20, 20]);
+    assert_eq!(tiles[1], [0x0, 0x55, 8, 8]);
+    assert_eq!(tiles[2], [0x0, 0x00, 0, 0]);
+
+    // sprites keep using the regular banks
+    cart.cpu_write(0x5127, 7);
+    mmc5_scanline(&mut cart, 1);
+    cart.cpu_read(0xFFFA);
+    assert_eq!(cart.ppu_read(0x1C00), 7);
+}
+
+#[test]
+fn mmc5_vertical_split()
+{
+    let mut cart = cartridge(image(5, 2, 8, 0x00));
+    cart.cpu_write(0x5104, 2);
+    cart.cpu_write(0x5C00 + 32 + 2, 0x07); // row 1, column 2
+    cart.cpu_write(0x5C00 + 0x3C0, 0b00001100); // palette 3 for the top right quadrant of the first attribute byte
+    cart.cpu_write(0x5104, 0);
+
+    // columns below 4 come from the split, scrolled down one row into CHR page 12
+    cart.cpu_write(0x5200, 0b10000100);
+    cart.cpu_write(0x5201, 8);
+    cart.cpu_write(0x5202, 3);
+
+    mmc5_prefetch(&mut cart, 0);
+    let tiles = mmc5_scanline(&mut cart, 0);
+    assert_eq!(tiles[0], [0x07, 0xFF, 12, 12]);
+    assert_eq!(tiles[1], [0x00, 0xFF, 12, 12]);
+    assert_eq!(tiles[2], [0x00, 0x00, 0, 0]);
+
+    // split on the right
+    cart.cpu_write(0x5200, 0b11000100);
+    let tiles = mmc5_scanline(&mut cart, 1);
+    assert_eq!(tiles[0], [0x00, 0x00, 0, 0]);
+    assert_eq!(tiles[2], [0x00, 0x00, 12, 12]);
+}
+
+#[test]
+fn mmc5_pulse_channels()
+{
+    let mut cart = cartridge(image(5, 2, 1, 0x00));
+    cart.cpu_write(0x5015, 0b01);
+    cart.cpu_write(0x5000, 0b10111000); // 50% duty, constant volume 8
+    cart.cpu_write(0x5002, 0x10);
+    cart.cpu_write(0x5003, 0x08);
+    assert_eq!(cart.cpu_read(0x5015), 0b01);
+
+    let mut high = 0;
+    let mut low = 0;
+    for _ in 0..0x400
+    {
+        cart.cpu_clock();
+        if cart.audio_output() > 0.0 { high += 1; } else { low += 1; }
+    }
+    assert!(high > 0x100 && low > 0x100);
+
+    // disabling clears the length counter
+    cart.cpu_write(0x5015, 0b00);
+    assert_eq!(cart.cpu_read(0x5015), 0b00);
+    assert_eq!(cart.audio_output(), 0.0);
+
+    // raw PCM, 0 writes are ignored
+    cart.cpu_write(0x5011, 0x80);
+    cart.cpu_write(0x5011, 0x00);
+    assert!(cart.audio_output() > 0.0);
+}
+
+#[test]
+fn mmc5_audio_runs_with_the_cpu()
+{
+    let mut bus = init_nes_bus();
+    bus.insert_cartridge(cartridge(image(5, 2, 1, 0x00)));
+    let mut cpu = init_cpu(bus);
+    for i in 0..0x800
+    {
+        cpu.get_bus_mut().write(i, 0xEA);
+    }
+    cpu.set_pc(0x0000);
+    cpu.get_bus_mut().write(0x5015, 0b10);
+    cpu.get_bus_mut().write(0x5004, 0b10111000);
+    cpu.get_bus_mut().write(0x5006, 0x10);
+    cpu.get_bus_mut().write(0x5007, 0x08);
+
+    // only stepping the CPU clocks the pulse timers
+    let mut high = 0;
+    let mut low = 0;
+    for _ in 0..0x200
+    {
+        cpu.step();
+        if cpu.get_bus().audio_sample() > 0.0 { high += 1; } else { low += 1; }
+    }
+    assert!(high > 0x40 && low > 0x40);
+}
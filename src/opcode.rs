@@ -1,3 +1,6 @@
+use self::Mnemonic::*;
+use self::AddressingMode::*;
+
 #[derive(Debug, Clone)]
 pub struct Opcode
 {
@@ -7,569 +10,580 @@ pub struct Opcode
     description: String,
 }
 
-pub fn set_opcode(code: u8, length: usize, cycles: u8, description: String) -> Opcode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mnemonic
 {
-    let op = Opcode { description, code, length, cycles };
-
-    return op;
-}
+    Adc, And, Asl, Bcc, Bcs, Beq, Bit, Bmi, Bne, Bpl, Brk, Bvc, Bvs, Clc,
+    Cld, Cli, Clv, Cmp, Cpx, Cpy, Dec, Dex, Dey, Eor, Inc, Inx, Iny, Jmp,
+    Jsr, Lda, Ldx, Ldy, Lsr, Nop, Ora, Pha, Php, Pla, Plp, Rol, Ror, Rti,
+    Rts, Sbc, Sec, Sed, Sei, Sta, Stx, Sty, Tax, Tay, Tsx, Txa, Txs, Tya,
 
-pub fn print_opcode(opcode: Opcode)
-{
-    println!("{:?}", opcode);
-}
+    // unofficial, stable
+    Slo, Rla, Sre, Rra, Sax, Lax, Dcp, Isc, Anc, Alr, Arr, Axs,
 
-pub fn get_opcode_length(opcode: Opcode) -> usize
-{
-    return opcode.length;
-}
-
-pub fn print_opcode_description(opcode: Opcode)
-{
-    println!("{}", opcode.description);
-}
-
-pub fn get_opcode_description(opcode: Opcode) -> String
-{
-    return opcode.description;
-}
+    // unofficial, unstable on real hardware
+    Xaa, Lxa, Ahx, Tas, Shy, Shx, Las,
 
-pub fn get_opcode_code(opcode: Opcode) -> u8
-{
-    return opcode.code;
+    // unofficial, locks up the CPU
+    Kil,
 }
 
-pub fn get_left_nibble(byte: u8) -> u8
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressingMode
 {
-    return (byte & 0xF0) >> 4;
+    Implied,
+    Accumulator,
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Relative,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    Indirect,
+    IndirectX,
+    IndirectY,
 }
 
-pub fn get_right_nibble(byte: u8) -> u8
+// One decoded slot of the 256 entry opcode table.
+// Both the disassembler and the CPU read from this table.
+#[derive(Debug, Clone, Copy)]
+pub struct OpcodeEntry
 {
-    return byte & 0x0F;
+    pub mnemonic: Mnemonic,
+    pub mode: AddressingMode,
+    pub length: usize, // opcode byte plus operand bytes
+    pub cycles: u8, // base cycles, before page crossing or branch penalties
+    pub page_cross_penalty: bool, // add 1 cycle if page boundary crossed
+    pub official: bool,
 }
 
-// This is going to find the appropriate
-// first part of the opcode. Then it's
-// going to call the appropriate function
-// to get the second part of the opcode.
-// That second function will call the Opcode
-// builder and return it back out.
-// pub fn start_opcode_build(byte: u8) -> Opcode
-pub fn build_opcode(byte: u8) -> Opcode
+impl Mnemonic
 {
-    let ln = get_left_nibble(byte);
-    let rn = get_right_nibble(byte);
-    let mut opcode = set_opcode(0x00, 0, 0, " ".to_string());
-
-    match ln
+    pub fn name(&self) -> &'static str
     {
-        0x0 =>
-        {
-            opcode = left_nib_is_0(rn);
-        },
-        0x1 =>
-        {
-            opcode = left_nib_is_1(rn);
-        },
-        0x2 =>
-        {
-            opcode = left_nib_is_2(rn);
-        },
-        0x3 =>
-        {
-            opcode = left_nib_is_3(rn);
-        },
-        0x4 =>
-        {
-            opcode = left_nib_is_4(rn);
-        },
-        0x5 =>
-        {
-            opcode = left_nib_is_5(rn);
-        },
-        0x6 =>
+        match *self
         {
-            opcode = left_nib_is_6(rn);
-        },
-        0x7 =>
-        {
-            opcode = left_nib_is_7(rn);
-        },
-        0x8 =>
-        {
-            opcode = left_nib_is_8(rn);
-        },
-        0x9 =>
-        {
-            opcode = left_nib_is_9(rn);
-        },
-        0xA =>
-        {
-            opcode = left_nib_is_a(rn);
-        },
-        0xB =>
-        {
-            opcode = left_nib_is_b(rn);
-        },
-        0xC =>
-        {
-            opcode = left_nib_is_c(rn);
-        },
-        0xD =>
-        {
-            opcode = left_nib_is_d(rn);
-        },
-        0xE =>
-        {
-            opcode = left_nib_is_e(rn);
-        },
-        0xF =>
+            Adc => "ADC", And => "AND", Asl => "ASL", Bcc => "BCC", Bcs => "BCS",
+            Beq => "BEQ", Bit => "BIT", Bmi => "BMI", Bne => "BNE", Bpl => "BPL",
+            Brk => "BRK", Bvc => "BVC", Bvs => "BVS", Clc => "CLC", Cld => "CLD",
+            Cli => "CLI", Clv => "CLV", Cmp => "CMP", Cpx => "CPX", Cpy => "CPY",
+            Dec => "DEC", Dex => "DEX", Dey => "DEY", Eor => "EOR", Inc => "INC",
+            Inx => "INX", Iny => "INY", Jmp => "JMP", Jsr => "JSR", Lda => "LDA",
+            Ldx => "LDX", Ldy => "LDY", Lsr => "LSR", Nop => "NOP", Ora => "ORA",
+            Pha => "PHA", Php => "PHP", Pla => "PLA", Plp => "PLP", Rol => "ROL",
+            Ror => "ROR", Rti => "RTI", Rts => "RTS", Sbc => "SBC", Sec => "SEC",
+            Sed => "SED", Sei => "SEI", Sta => "STA", Stx => "STX", Sty => "STY",
+            Tax => "TAX", Tay => "TAY", Tsx => "TSX", Txa => "TXA", Txs => "TXS",
+            Tya => "TYA", Slo => "SLO", Rla => "RLA", Sre => "SRE", Rra => "RRA",
+            Sax => "SAX", Lax => "LAX", Dcp => "DCP", Isc => "ISC", Anc => "ANC",
+            Alr => "ALR", Arr => "ARR", Axs => "AXS", Xaa => "XAA", Lxa => "LXA",
+            Ahx => "AHX", Tas => "TAS", Shy => "SHY", Shx => "SHX", Las => "LAS",
+            Kil => "KIL",
+        }
+    }
+
+    pub fn long_name(&self) -> &'static str
+    {
+        match *self
         {
-            opcode = left_nib_is_f(rn);
-        },
-        _   => println!("Unsure about this nib."),
+            Adc => "ADd with Carry",
+            And => "bitwise AND with accumulator",
+            Asl => "Arithmetic Shift Left",
+            Bcc => "Branch on Carry Clear",
+            Bcs => "Branch on Carry Set",
+            Beq => "Branch on EQual",
+            Bit => "test BITs",
+            Bmi => "Branch on MInus",
+            Bne => "Branch on Not Equal",
+            Bpl => "Branch on PLus",
+            Brk => "BReaK",
+            Bvc => "Branch on oVerflow Clear",
+            Bvs => "Branch on oVerflow Set",
+            Clc => "CLear Carry flag",
+            Cld => "CLear Decimal flag",
+            Cli => "CLear Interrupt flag",
+            Clv => "CLear oVerflow flag",
+            Cmp => "CoMPare accumulator",
+            Cpx => "ComPare X register",
+            Cpy => "ComPare Y register",
+            Dec => "DECrement memory",
+            Dex => "DEcrement X",
+            Dey => "DEcrement Y",
+            Eor => "bitwise Exclusive OR",
+            Inc => "INCrement memory",
+            Inx => "INcrement X",
+            Iny => "INcrement Y",
+            Jmp => "JuMP",
+            Jsr => "Jump to SubRoutine",
+            Lda => "LoaD Accumulator",
+            Ldx => "LoaD X register",
+            Ldy => "LoaD Y register",
+            Lsr => "Logical Shift Right",
+            Nop => "No OPeration",
+            Ora => "bitwise OR with Accumulator",
+            Pha => "PusH Accumulator",
+            Php => "PusH Processor status",
+            Pla => "PuLl Accumulator",
+            Plp => "PuLl Processor status",
+            Rol => "ROtate Left",
+            Ror => "ROtate Right",
+            Rti => "ReTurn from Interrupt",
+            Rts => "ReTurn from Subroutine",
+            Sbc => "SuBtract with Carry",
+            Sec => "SEt Carry flag",
+            Sed => "SEt Decimal flag",
+            Sei => "SEt Interrupt flag",
+            Sta => "STore Accumulator",
+            Stx => "STore X register",
+            Sty => "STore Y register",
+            Tax => "Transfer A to X",
+            Tay => "Transfer A to Y",
+            Tsx => "Transfer Stack pointer to X",
+            Txa => "Transfer X to A",
+            Txs => "Transfer X to Stack pointer",
+            Tya => "Transfer Y to A",
+            Slo => "Shift Left then Or with accumulator",
+            Rla => "Rotate Left then And with accumulator",
+            Sre => "Shift Right then Eor with accumulator",
+            Rra => "Rotate Right then Add with carry",
+            Sax => "Store A AND X",
+            Lax => "Load Accumulator and X register",
+            Dcp => "DeCrement then comPare",
+            Isc => "Increment then Subtract with Carry",
+            Anc => "AND then copy N to Carry",
+            Alr => "AND then Logical shift Right",
+            Arr => "AND then Rotate Right",
+            Axs => "A AND X minus immediate into X",
+            Xaa => "transfer X AND immediate to A",
+            Lxa => "Load X and A with immediate",
+            Ahx => "store A AND X AND High byte",
+            Tas => "Transfer A AND X to Stack pointer",
+            Shy => "Store Y AND High byte",
+            Shx => "Store X AND High byte",
+            Las => "Load A, X and S with memory AND S",
+            Kil => "KILl, jams the CPU",
+        }
     }
-    return opcode;
 }
 
-fn left_nib_is_0(rn: u8) -> Opcode
+impl AddressingMode
 {
-    let mut opcode = set_opcode(0x00, 0, 0, " ".to_string());
-    match rn
+    // number of operand bytes following the opcode
+    pub const fn operand_length(&self) -> usize
     {
-        0x0 => opcode = set_opcode(0x00, 1, 7, "BRK".to_string()),
-        // add a cycle if page boundary crossed
-        0x1 => opcode = set_opcode(0x01, 2, 6, "ORA Indirect, X".to_string()),
-        0x2 => opcode = set_opcode(0x02, 0, 0, "Not used.".to_string()),
-        0x3 => opcode = set_opcode(0x03, 0, 0, "Not used.".to_string()),
-        0x4 => opcode = set_opcode(0x04, 0, 0, "Not used.".to_string()),
-        0x5 => opcode = set_opcode(0x05, 2, 2, "ORA Zero Page".to_string()),
-        0x6 => opcode = set_opcode(0x06, 2, 5, "ASL Zero Page".to_string()),
-        0x7 => opcode = set_opcode(0x07, 0 ,0, "Not used.".to_string()),
-        0x8 => opcode = set_opcode(0x08, 0 , 3, "PHP (Push processor status)".to_string()),
-        0x9 => opcode = set_opcode(0x09, 2, 2, "ORA Immediate".to_string()),
-        0xA => opcode = set_opcode(0x0A, 1, 2, "ASL Accumulator".to_string()),
-        0xB => opcode = set_opcode(0x0B, 0, 0, "Not used.".to_string()),
-        0xC => opcode = set_opcode(0x0C, 0, 0, "Not used.".to_string()),
-        0xD => opcode = set_opcode(0x0D, 3, 4, "ORA Absolute".to_string()),
-        0xE => opcode = set_opcode(0x0E, 3, 6, "ASL Absolute".to_string()),
-        0xF => opcode = set_opcode(0x0F, 0, 0, "Not used.".to_string()),
-        _   => println!("Not used."),
+        match *self
+        {
+            Implied | Accumulator => 0,
+            Immediate | ZeroPage | ZeroPageX | ZeroPageY | Relative | IndirectX | IndirectY => 1,
+            Absolute | AbsoluteX | AbsoluteY | Indirect => 2,
+        }
     }
-    return opcode;
-}
 
-fn left_nib_is_1(rn: u8) -> Opcode
-{
-    let mut opcode = set_opcode(0x00, 0, 0, " ".to_string());
-    match rn
+    pub fn description(&self) -> &'static str
     {
-        // add 1 to cycles if branch is taken, and 1 more if page crossing
-        0x0 => opcode = set_opcode(0x10, 2, 2, "BPL (Branch on PLus)".to_string()),
-        // add 1 cycle if page boundary crossed
-        0x1 => opcode = set_opcode(0x11, 2, 5, "ORA Indirect, Y".to_string()),
-        0x2 => opcode = set_opcode(0x12, 0, 0, "Not used.".to_string()),
-        0x3 => opcode = set_opcode(0x13, 0, 0, "Not used.".to_string()),
-        0x4 => opcode = set_opcode(0x14, 0, 0, "Not used.".to_string()),
-        0x5 => opcode = set_opcode(0x15, 2, 3, "ORA Zero Page, X".to_string()),
-        0x6 => opcode = set_opcode(0x16, 2, 6, "ASL Zero Page, X".to_string()),
-        0x7 => opcode = set_opcode(0x17, 0, 0, "Not used.".to_string()),
-        0x8 => opcode = set_opcode(0x18, 1, 2, "CLC (Clear Carry flag)".to_string()),
-        // add 1 cycle if page boundary crossed
-        0x9 => opcode = set_opcode(0x19, 3, 4, "ORA Absolute, Y".to_string()),
-        0xA => opcode = set_opcode(0x1A, 0, 0, "Not used.".to_string()),
-        0xB => opcode = set_opcode(0x1B, 0, 0, "Not used.".to_string()),
-        0xC => opcode = set_opcode(0x1C, 0, 0, "Not used.".to_string()),
-        // add 1 cycle if page boundary crossed
-        0xD => opcode = set_opcode(0x1D, 3, 4, "ORA Absolute, X".to_string()),
-        0xE => opcode = set_opcode(0x1E, 3, 7, "ASL Absolute, X".to_string()),
-        0xF => opcode = set_opcode(0x1F, 0, 0, "Not used.".to_string()),
-        _   => println!("Not used."),
+        match *self
+        {
+            Implied => "",
+            Accumulator => "Accumulator",
+            Immediate => "Immediate",
+            ZeroPage => "Zero Page",
+            ZeroPageX => "Zero Page, X",
+            ZeroPageY => "Zero Page, Y",
+            Relative => "",
+            Absolute => "Absolute",
+            AbsoluteX => "Absolute, X",
+            AbsoluteY => "Absolute, Y",
+            Indirect => "Indirect",
+            IndirectX => "Indirect, X",
+            IndirectY => "Indirect, Y",
+        }
     }
-    return opcode;
 }
 
-fn left_nib_is_2(rn: u8) -> Opcode
+const fn official(mnemonic: Mnemonic, mode: AddressingMode, cycles: u8, page_cross_penalty: bool) -> OpcodeEntry
 {
-    let mut opcode = set_opcode(0x00, 0, 0, " ".to_string());
-    match rn
+    return OpcodeEntry
     {
-        0x0 => opcode = set_opcode(0x20, 3, 6, "JSR (Jump to SubRoutine)".to_string()),
-        0x1 => opcode = set_opcode(0x21, 2, 6, "AND Indirect, X".to_string()),
-        0x2 => opcode = set_opcode(0x22, 0, 0, "Not used.".to_string()),
-        0x3 => opcode = set_opcode(0x23, 0, 0, "Not used.".to_string()),
-        0x4 => opcode = set_opcode(0x24, 2, 3, "BIT (test BITs) Zero Page".to_string()),
-        0x5 => opcode = set_opcode(0x25, 2, 2, "AND Zero Page".to_string()),
-        0x6 => opcode = set_opcode(0x26, 2, 5, "ROL (ROtate Left) Zero Page".to_string()),
-        0x7 => opcode = set_opcode(0x27, 0, 0, "Not used.".to_string()),
-        0x8 => opcode = set_opcode(0x28, 1, 4, "PLP (PuLl Processor status)".to_string()),
-        0x9 => opcode = set_opcode(0x29, 2, 2, "AND Immediate".to_string()),
-        0xA => opcode = set_opcode(0x2A, 1, 2, "ROL (ROtate Left) Accumulator".to_string()),
-        0xB => opcode = set_opcode(0x2B, 0, 0, "Not used.".to_string()),
-        0xC => opcode = set_opcode(0x2C, 3, 4, "BIT (test BITs) Absolute".to_string()),
-        0xD => opcode = set_opcode(0x2D, 3, 4, "AND Absolute".to_string()),
-        0xE => opcode = set_opcode(0x2E, 3, 6, "ROL (ROtate Left) Absolute".to_string()),
-        0xF => opcode = set_opcode(0x2F, 0, 0, "Not used.".to_string()),
-        _   => println!("Not used."),
-    }
-    return opcode;
+        mnemonic,
+        mode,
+        length: 1 + mode.operand_length(),
+        cycles,
+        page_cross_penalty,
+        official: true,
+    };
 }
 
-fn left_nib_is_3(rn: u8) -> Opcode
+const fn unofficial(mnemonic: Mnemonic, mode: AddressingMode, cycles: u8, page_cross_penalty: bool) -> OpcodeEntry
 {
-    let mut opcode = set_opcode(0x00, 0, 0, " ".to_string());
-    match rn
+    return OpcodeEntry
     {
-        0x0 => opcode = set_opcode(0x30, 2, 2, "BMI (Branch on MInus)".to_string()),
-        // add 1 cycle if page boundary crossed
-        0x1 => opcode = set_opcode(0x31, 2, 5, "AND Indirect, Y".to_string()),
-        0x2 => opcode = set_opcode(0x32, 0, 0, "Not used.".to_string()),
-        0x3 => opcode = set_opcode(0x33, 0, 0, "Not used.".to_string()),
-        0x4 => opcode = set_opcode(0x34, 0, 0, "Not used.".to_string()),
-        0x5 => opcode = set_opcode(0x35, 2, 3, "AND Zero Page, X".to_string()),
-        0x6 => opcode = set_opcode(0x36, 2, 6, "ROL (ROtate Left) Zero Page, X".to_string()),
-        0x7 => opcode = set_opcode(0x37, 0, 0, "Not used.".to_string()),
-        0x8 => opcode = set_opcode(0x38, 1, 2, "SEC (SEt Carry)".to_string()),
-        // add 1 cycle if page boundary is crossed
-        0x9 => opcode = set_opcode(0x39, 3, 4, "AND Absolute, Y".to_string()),
-        0xA => opcode = set_opcode(0x3A, 0, 0, "Not used.".to_string()),
-        0xB => opcode = set_opcode(0x3B, 0, 0, "Not used.".to_string()),
-        0xC => opcode = set_opcode(0x3C, 0, 0, "Not used.".to_string()),
-        // add 1 cycle if page boundary is crossed
-        0xD => opcode = set_opcode(0x3D, 3, 4, "AND Absolute, X".to_string()),
-        0xE => opcode = set_opcode(0x3E, 3, 7, "ROL (ROtate Left) Absolute, X".to_string()),
-        0xF => opcode = set_opcode(0x3F, 0, 0, "Not used.".to_string()),
-        _   => println!("Not used."),
-    }
-    return opcode;
+        mnemonic,
+        mode,
+        length: 1 + mode.operand_length(),
+        cycles,
+        page_cross_penalty,
+        official: false,
+    };
 }
 
-fn left_nib_is_4(rn: u8) -> Opcode
+// Indexed directly by opcode byte.
+static OPCODE_TABLE: [OpcodeEntry; 256] =
+[
+    official(Brk, Implied, 7, false),               // 0x00
+    official(Ora, IndirectX, 6, false),             // 0x01
+    unofficial(Kil, Implied, 2, false),             // 0x02
+    unofficial(Slo, IndirectX, 8, false),           // 0x03
+    unofficial(Nop, ZeroPage, 3, false),            // 0x04
+    official(Ora, ZeroPage, 3, false),              // 0x05
+    official(Asl, ZeroPage, 5, false),              // 0x06
+    unofficial(Slo, ZeroPage, 5, false),            // 0x07
+    official(Php, Implied, 3, false),               // 0x08
+    official(Ora, Immediate, 2, false),             // 0x09
+    official(Asl, Accumulator, 2, false),           // 0x0A
+    unofficial(Anc, Immediate, 2, false),           // 0x0B
+    unofficial(Nop, Absolute, 4, false),            // 0x0C
+    official(Ora, Absolute, 4, false),              // 0x0D
+    official(Asl, Absolute, 6, false),              // 0x0E
+    unofficial(Slo, Absolute, 6, false),            // 0x0F
+    official(Bpl, Relative, 2, false),              // 0x10
+    official(Ora, IndirectY, 5, true),              // 0x11
+    unofficial(Kil, Implied, 2, false),             // 0x12
+    unofficial(Slo, IndirectY, 8, false),           // 0x13
+    unofficial(Nop, ZeroPageX, 4, false),           // 0x14
+    official(Ora, ZeroPageX, 4, false),             // 0x15
+    official(Asl, ZeroPageX, 6, false),             // 0x16
+    unofficial(Slo, ZeroPageX, 6, false),           // 0x17
+    official(Clc, Implied, 2, false),               // 0x18
+    official(Ora, AbsoluteY, 4, true),              // 0x19
+    unofficial(Nop, Implied, 2, false),             // 0x1A
+    unofficial(Slo, AbsoluteY, 7, false),           // 0x1B
+    unofficial(Nop, AbsoluteX, 4, true),            // 0x1C
+    official(Ora, AbsoluteX, 4, true),              // 0x1D
+    official(Asl, AbsoluteX, 7, false),             // 0x1E
+    unofficial(Slo, AbsoluteX, 7, false),           // 0x1F
+    official(Jsr, Absolute, 6, false),              // 0x20
+    official(And, IndirectX, 6, false),             // 0x21
+    unofficial(Kil, Implied, 2, false),             // 0x22
+    unofficial(Rla, IndirectX, 8, false),           // 0x23
+    official(Bit, ZeroPage, 3, false),              // 0x24
+    official(And, ZeroPage, 3, false),              // 0x25
+    official(Rol, ZeroPage, 5, false),              // 0x26
+    unofficial(Rla, ZeroPage, 5, false),            // 0x27
+    official(Plp, Implied, 4, false),               // 0x28
+    official(And, Immediate, 2, false),             // 0x29
+    official(Rol, Accumulator, 2, false),           // 0x2A
+    unofficial(Anc, Immediate, 2, false),           // 0x2B
+    official(Bit, Absolute, 4, false),              // 0x2C
+    official(And, Absolute, 4, false),              // 0x2D
+    official(Rol, Absolute, 6, false),              // 0x2E
+    unofficial(Rla, Absolute, 6, false),            // 0x2F
+    official(Bmi, Relative, 2, false),              // 0x30
+    official(And, IndirectY, 5, true),              // 0x31
+    unofficial(Kil, Implied, 2, false),             // 0x32
+    unofficial(Rla, IndirectY, 8, false),           // 0x33
+    unofficial(Nop, ZeroPageX, 4, false),           // 0x34
+    official(And, ZeroPageX, 4, false),             // 0x35
+    official(Rol, ZeroPageX, 6, false),             // 0x36
+    unofficial(Rla, ZeroPageX, 6, false),           // 0x37
+    official(Sec, Implied, 2, false),               // 0x38
+    official(And, AbsoluteY, 4, true),              // 0x39
+    unofficial(Nop, Implied, 2, false),             // 0x3A
+    unofficial(Rla, AbsoluteY, 7, false),           // 0x3B
+    unofficial(Nop, AbsoluteX, 4, true),            // 0x3C
+    official(And, AbsoluteX, 4, true),              // 0x3D
+    official(Rol, AbsoluteX, 7, false),             // 0x3E
+    unofficial(Rla, AbsoluteX, 7, false),           // 0x3F
+    official(Rti, Implied, 6, false),               // 0x40
+    official(Eor, IndirectX, 6, false),             // 0x41
+    unofficial(Kil, Implied, 2, false),             // 0x42
+    unofficial(Sre, IndirectX, 8, false),           // 0x43
+    unofficial(Nop, ZeroPage, 3, false),            // 0x44
+    official(Eor, ZeroPage, 3, false),              // 0x45
+    official(Lsr, ZeroPage, 5, false),              // 0x46
+    unofficial(Sre, ZeroPage, 5, false),            // 0x47
+    official(Pha, Implied, 3, false),               // 0x48
+    official(Eor, Immediate, 2, false),             // 0x49
+    official(Lsr, Accumulator, 2, false),           // 0x4A
+    unofficial(Alr, Immediate, 2, false),           // 0x4B
+    official(Jmp, Absolute, 3, false),              // 0x4C
+    official(Eor, Absolute, 4, false),              // 0x4D
+    official(Lsr, Absolute, 6, false),              // 0x4E
+    unofficial(Sre, Absolute, 6, false),            // 0x4F
+    official(Bvc, Relative, 2, false),              // 0x50
+    official(Eor, IndirectY, 5, true),              // 0x51
+    unofficial(Kil, Implied, 2, false),             // 0x52
+    unofficial(Sre, IndirectY, 8, false),           // 0x53
+    unofficial(Nop, ZeroPageX, 4, false),           // 0x54
+    official(Eor, ZeroPageX, 4, false),             // 0x55
+    official(Lsr, ZeroPageX, 6, false),             // 0x56
+    unofficial(Sre, ZeroPageX, 6, false),           // 0x57
+    official(Cli, Implied, 2, false),               // 0x58
+    official(Eor, AbsoluteY, 4, true),              // 0x59
+    unofficial(Nop, Implied, 2, false),             // 0x5A
+    unofficial(Sre, AbsoluteY, 7, false),           // 0x5B
+    unofficial(Nop, AbsoluteX, 4, true),            // 0x5C
+    official(Eor, AbsoluteX, 4, true),              // 0x5D
+    official(Lsr, AbsoluteX, 7, false),             // 0x5E
+    unofficial(Sre, AbsoluteX, 7, false),           // 0x5F
+    official(Rts, Implied, 6, false),               // 0x60
+    official(Adc, IndirectX, 6, false),             // 0x61
+    unofficial(Kil, Implied, 2, false),             // 0x62
+    unofficial(Rra, IndirectX, 8, false),           // 0x63
+    unofficial(Nop, ZeroPage, 3, false),            // 0x64
+    official(Adc, ZeroPage, 3, false),              // 0x65
+    official(Ror, ZeroPage, 5, false),              // 0x66
+    unofficial(Rra, ZeroPage, 5, false),            // 0x67
+    official(Pla, Implied, 4, false),               // 0x68
+    official(Adc, Immediate, 2, false),             // 0x69
+    official(Ror, Accumulator, 2, false),           // 0x6A
+    unofficial(Arr, Immediate, 2, false),           // 0x6B
+    official(Jmp, Indirect, 5, false),              // 0x6C
+    official(Adc, Absolute, 4, false),              // 0x6D
+    official(Ror, Absolute, 6, false),              // 0x6E
+    unofficial(Rra, Absolute, 6, false),            // 0x6F
+    official(Bvs, Relative, 2, false),              // 0x70
+    official(Adc, IndirectY, 5, true),              // 0x71
+    unofficial(Kil, Implied, 2, false),             // 0x72
+    unofficial(Rra, IndirectY, 8, false),           // 0x73
+    unofficial(Nop, ZeroPageX, 4, false),           // 0x74
+    official(Adc, ZeroPageX, 4, false),             // 0x75
+    official(Ror, ZeroPageX, 6, false),             // 0x76
+    unofficial(Rra, ZeroPageX, 6, false),           // 0x77
+    official(Sei, Implied, 2, false),               // 0x78
+    official(Adc, AbsoluteY, 4, true),              // 0x79
+    unofficial(Nop, Implied, 2, false),             // 0x7A
+    unofficial(Rra, AbsoluteY, 7, false),           // 0x7B
+    unofficial(Nop, AbsoluteX, 4, true),            // 0x7C
+    official(Adc, AbsoluteX, 4, true),              // 0x7D
+    official(Ror, AbsoluteX, 7, false),             // 0x7E
+    unofficial(Rra, AbsoluteX, 7, false),           // 0x7F
+    unofficial(Nop, Immediate, 2, false),           // 0x80
+    official(Sta, IndirectX, 6, false),             // 0x81
+    unofficial(Nop, Immediate, 2, false),           // 0x82
+    unofficial(Sax, IndirectX, 6, false),           // 0x83
+    official(Sty, ZeroPage, 3, false),              // 0x84
+    official(Sta, ZeroPage, 3, false),              // 0x85
+    official(Stx, ZeroPage, 3, false),              // 0x86
+    unofficial(Sax, ZeroPage, 3, false),            // 0x87
+    official(Dey, Implied, 2, false),               // 0x88
+    unofficial(Nop, Immediate, 2, false),           // 0x89
+    official(Txa, Implied, 2, false),               // 0x8A
+    unofficial(Xaa, Immediate, 2, false),           // 0x8B
+    official(Sty, Absolute, 4, false),              // 0x8C
+    official(Sta, Absolute, 4, false),              // 0x8D
+    official(Stx, Absolute, 4, false),              // 0x8E
+    unofficial(Sax, Absolute, 4, false),            // 0x8F
+    official(Bcc, Relative, 2, false),              // 0x90
+    official(Sta, IndirectY, 6, false),             // 0x91
+    unofficial(Kil, Implied, 2, false),             // 0x92
+    unofficial(Ahx, IndirectY, 6, false),           // 0x93
+    official(Sty, ZeroPageX, 4, false),             // 0x94
+    official(Sta, ZeroPageX, 4, false),             // 0x95
+    official(Stx, ZeroPageY, 4, false),             // 0x96
+    unofficial(Sax, ZeroPageY, 4, false),           // 0x97
+    official(Tya, Implied, 2, false),               // 0x98
+    official(Sta, AbsoluteY, 5, false),             // 0x99
+    official(Txs, Implied, 2, false),               // 0x9A
+    unofficial(Tas, AbsoluteY, 5, false),           // 0x9B
+    unofficial(Shy, AbsoluteX, 5, false),           // 0x9C
+    official(Sta, AbsoluteX, 5, false),             // 0x9D
+    unofficial(Shx, AbsoluteY, 5, false),           // 0x9E
+    unofficial(Ahx, AbsoluteY, 5, false),           // 0x9F
+    official(Ldy, Immediate, 2, false),             // 0xA0
+    official(Lda, IndirectX, 6, false),             // 0xA1
+    official(Ldx, Immediate, 2, false),             // 0xA2
+    unofficial(Lax, IndirectX, 6, false),           // 0xA3
+    official(Ldy, ZeroPage, 3, false),              // 0xA4
+    official(Lda, ZeroPage, 3, false),              // 0xA5
+    official(Ldx, ZeroPage, 3, false),              // 0xA6
+    unofficial(Lax, ZeroPage, 3, false),            // 0xA7
+    official(Tay, Implied, 2, false),               // 0xA8
+    official(Lda, Immediate, 2, false),             // 0xA9
+    official(Tax, Implied, 2, false),               // 0xAA
+    unofficial(Lxa, Immediate, 2, false),           // 0xAB
+    official(Ldy, Absolute, 4, false),              // 0xAC
+    official(Lda, Absolute, 4, false),              // 0xAD
+    official(Ldx, Absolute, 4, false),              // 0xAE
+    unofficial(Lax, Absolute, 4, false),            // 0xAF
+    official(Bcs, Relative, 2, false),              // 0xB0
+    official(Lda, IndirectY, 5, true),              // 0xB1
+    unofficial(Kil, Implied, 2, false),             // 0xB2
+    unofficial(Lax, IndirectY, 5, true),            // 0xB3
+    official(Ldy, ZeroPageX, 4, false),             // 0xB4
+    official(Lda, ZeroPageX, 4, false),             // 0xB5
+    official(Ldx, ZeroPageY, 4, false),             // 0xB6
+    unofficial(Lax, ZeroPageY, 4, false),           // 0xB7
+    official(Clv, Implied, 2, false),               // 0xB8
+    official(Lda, AbsoluteY, 4, true),              // 0xB9
+    official(Tsx, Implied, 2, false),               // 0xBA
+    unofficial(Las, AbsoluteY, 4, true),            // 0xBB
+    official(Ldy, AbsoluteX, 4, true),              // 0xBC
+    official(Lda, AbsoluteX, 4, true),              // 0xBD
+    official(Ldx, AbsoluteY, 4, true),              // 0xBE
+    unofficial(Lax, AbsoluteY, 4, true),            // 0xBF
+    official(Cpy, Immediate, 2, false),             // 0xC0
+    official(Cmp, IndirectX, 6, false),             // 0xC1
+    unofficial(Nop, Immediate, 2, false),           // 0xC2
+    unofficial(Dcp, IndirectX, 8, false),           // 0xC3
+    official(Cpy, ZeroPage, 3, false),              // 0xC4
+    official(Cmp, ZeroPage, 3, false),              // 0xC5
+    official(Dec, ZeroPage, 5, false),              // 0xC6
+    unofficial(Dcp, ZeroPage, 5, false),            // 0xC7
+    official(Iny, Implied, 2, false),               // 0xC8
+    official(Cmp, Immediate, 2, false),             // 0xC9
+    official(Dex, Implied, 2, false),               // 0xCA
+    unofficial(Axs, Immediate, 2, false),           // 0xCB
+    official(Cpy, Absolute, 4, false),              // 0xCC
+    official(Cmp, Absolute, 4, false),              // 0xCD
+    official(Dec, Absolute, 6, false),              // 0xCE
+    unofficial(Dcp, Absolute, 6, false),            // 0xCF
+    official(Bne, Relative, 2, false),              // 0xD0
+    official(Cmp, IndirectY, 5, true),              // 0xD1
+    unofficial(Kil, Implied, 2, false),             // 0xD2
+    unofficial(Dcp, IndirectY, 8, false),           // 0xD3
+    unofficial(Nop, ZeroPageX, 4, false),           // 0xD4
+    official(Cmp, ZeroPageX, 4, false),             // 0xD5
+    official(Dec, ZeroPageX, 6, false),             // 0xD6
+    unofficial(Dcp, ZeroPageX, 6, false),           // 0xD7
+    official(Cld, Implied, 2, false),               // 0xD8
+    official(Cmp, AbsoluteY, 4, true),              // 0xD9
+    unofficial(Nop, Implied, 2, false),             // 0xDA
+    unofficial(Dcp, AbsoluteY, 7, false),           // 0xDB
+    unofficial(Nop, AbsoluteX, 4, true),            // 0xDC
+    official(Cmp, AbsoluteX, 4, true),              // 0xDD
+    official(Dec, AbsoluteX, 7, false),             // 0xDE
+    unofficial(Dcp, AbsoluteX, 7, false),           // 0xDF
+    official(Cpx, Immediate, 2, false),             // 0xE0
+    official(Sbc, IndirectX, 6, false),             // 0xE1
+    unofficial(Nop, Immediate, 2, false),           // 0xE2
+    unofficial(Isc, IndirectX, 8, false),           // 0xE3
+    official(Cpx, ZeroPage, 3, false),              // 0xE4
+    official(Sbc, ZeroPage, 3, false),              // 0xE5
+    official(Inc, ZeroPage, 5, false),              // 0xE6
+    unofficial(Isc, ZeroPage, 5, false),            // 0xE7
+    official(Inx, Implied, 2, false),               // 0xE8
+    official(Sbc, Immediate, 2, false),             // 0xE9
+    official(Nop, Implied, 2, false),               // 0xEA
+    unofficial(Sbc, Immediate, 2, false),           // 0xEB
+    official(Cpx, Absolute, 4, false),              // 0xEC
+    official(Sbc, Absolute, 4, false),              // 0xED
+    official(Inc, Absolute, 6, false),              // 0xEE
+    unofficial(Isc, Absolute, 6, false),            // 0xEF
+    official(Beq, Relative, 2, false),              // 0xF0
+    official(Sbc, IndirectY, 5, true),              // 0xF1
+    unofficial(Kil, Implied, 2, false),             // 0xF2
+    unofficial(Isc, IndirectY, 8, false),           // 0xF3
+    unofficial(Nop, ZeroPageX, 4, false),           // 0xF4
+    official(Sbc, ZeroPageX, 4, false),             // 0xF5
+    official(Inc, ZeroPageX, 6, false),             // 0xF6
+    unofficial(Isc, ZeroPageX, 6, false),           // 0xF7
+    official(Sed, Implied, 2, false),               // 0xF8
+    official(Sbc, AbsoluteY, 4, true),              // 0xF9
+    unofficial(Nop, Implied, 2, false),             // 0xFA
+    unofficial(Isc, AbsoluteY, 7, false),           // 0xFB
+    unofficial(Nop, AbsoluteX, 4, true),            // 0xFC
+    official(Sbc, AbsoluteX, 4, true),              // 0xFD
+    official(Inc, AbsoluteX, 7, false),             // 0xFE
+    unofficial(Isc, AbsoluteX, 7, false),           // 0xFF
+];
+
+pub fn decode(code: u8) -> OpcodeEntry
 {
-    let mut opcode = set_opcode(0x00, 0, 0, " ".to_string());
-    match rn
-    {
-        0x0 => opcode = set_opcode(0x40, 1, 6, "RTI (ReTurn from Interrupt)".to_string()),
-        0x1 => opcode = set_opcode(0x41, 2, 6, "EOR Indirect, X".to_string()),
-        0x2 => opcode = set_opcode(0x42, 0, 0, "Not used.".to_string()),
-        0x3 => opcode = set_opcode(0x43, 0, 0, "Not used.".to_string()),
-        0x4 => opcode = set_opcode(0x44, 0, 0, "Not used.".to_string()),
-        0x5 => opcode = set_opcode(0x45, 2, 3, "EOR Zero Page".to_string()),
-        0x6 => opcode = set_opcode(0x46, 2, 5, "LSR (Logical Shift Right) Zero Page".to_string()),
-        0x7 => opcode = set_opcode(0x47, 0, 0, "Not used.".to_string()),
-        0x8 => opcode = set_opcode(0x48, 1, 3, "PHA (PusH Accumulator)".to_string()),
-        0x9 => opcode = set_opcode(0x49, 2, 2, "EOR Immediate".to_string()),
-        0xA => opcode = set_opcode(0x4A, 1, 2, "LSR (Logical Shift Right) Accumulator".to_string()),
-        0xB => opcode = set_opcode(0x4B, 0, 0, "Not used.".to_string()),
-        0xC => opcode = set_opcode(0x4C, 3, 3, "JMP Absolute".to_string()),
-        0xD => opcode = set_opcode(0x4D, 3, 4, "EOR Absolute".to_string()),
-        0xE => opcode = set_opcode(0x4E, 3, 6, "LSR (Logical Shift Right) Absolute".to_string()),
-        0xF => opcode = set_opcode(0x4F, 0, 0, "Not used.".to_string()),
-        _   => println!("Not used."),
-    }
-    return opcode;
+    return OPCODE_TABLE[code as usize];
 }
 
-fn left_nib_is_5(rn: u8) -> Opcode
+pub fn set_opcode(code: u8, length: usize, cycles: u8, description: String) -> Opcode
 {
-    let mut opcode = set_opcode(0x00, 0, 0, " ".to_string());
-    match rn
-    {
-        0x0 => opcode = set_opcode(0x50, 2, 2, "BVC (Branch on oVerflow Clear)".to_string()),
-        // add 1 cycle if page boundary crossed
-        0x1 => opcode = set_opcode(0x51, 2, 5, "EOR Indirect, Y".to_string()),
-        0x2 => opcode = set_opcode(0x52, 0, 0, "Not used.".to_string()),
-        0x3 => opcode = set_opcode(0x53, 0, 0, "Not used.".to_string()),
-        0x4 => opcode = set_opcode(0x54, 0, 0, "Not used.".to_string()),
-        0x5 => opcode = set_opcode(0x55, 2, 4, "EOR Zero Page, X".to_string()),
-        0x6 => opcode = set_opcode(0x56, 2, 6, "LSR (Logical Shift Right) Zero Page, X".to_string()),
-        0x7 => opcode = set_opcode(0x57, 0, 0, "Not used.".to_string()),
-        0x8 => opcode = set_opcode(0x58, 1, 2, "CLI (CLear Interrupt)".to_string()),
-        // add 1 cycle if page boundary is crossed
-        0x9 => opcode = set_opcode(0x59, 3, 4, "EOR Absolute, Y".to_string()),
-        0xA => opcode = set_opcode(0x5A, 0, 0, "Not used.".to_string()),
-        0xB => opcode = set_opcode(0x5B, 0, 0, "Not used.".to_string()),
-        0xC => opcode = set_opcode(0x5C, 0, 0, "Not used.".to_string()),
-        // add 1 cycle if page boundary is crossed
-        0xD => opcode = set_opcode(0x5D, 3, 4, "EOR Absolute, X".to_string()),
-        0xE => opcode = set_opcode(0x5E, 3, 7, "LSR (Logical Shift Right) Absolute, X".to_string()),
-        0xF => opcode = set_opcode(0x5F, 0, 0, "Not used.".to_string()),
-        _   => println!("Not used."),
-    }
-    return opcode;
+    let op = Opcode { description, code, length, cycles };
+
+    return op;
 }
 
-fn left_nib_is_6(rn: u8) -> Opcode
+pub fn print_opcode(opcode: Opcode)
 {
-    let mut opcode = set_opcode(0x00, 0, 0, " ".to_string());
-    match rn
-    {
-        0x0 => opcode = set_opcode(0x60, 1, 6, "RTS (ReTurn from Subroutine)".to_string()),
-        0x1 => opcode = set_opcode(0x61, 2, 6, "ADC Indirect, X".to_string()),
-        0x2 => opcode = set_opcode(0x62, 0, 0, "Not used.".to_string()),
-        0x3 => opcode = set_opcode(0x63, 0, 0, "Not used.".to_string()),
-        0x4 => opcode = set_opcode(0x64, 0, 0, "Not used.".to_string()),
-        0x5 => opcode = set_opcode(0x65, 2, 3, "ADC Zero Page".to_string()),
-        0x6 => opcode = set_opcode(0x66, 2, 5, "ROR (ROtate Right) Zero Page".to_string()),
-        0x7 => opcode = set_opcode(0x67, 0, 0, "Not used.".to_string()),
-        0x8 => opcode = set_opcode(0x68, 1, 4, "PLA (PuLl Accumulator)".to_string()),
-        0x9 => opcode = set_opcode(0x69, 2, 2, "ADC Immediate".to_string()),
-        0xA => opcode = set_opcode(0x6A, 1, 2, "ROR (ROtate Right)".to_string()),
-        0xB => opcode = set_opcode(0x6B, 0, 0, "Not used.".to_string()),
-        0xC => opcode = set_opcode(0x6C, 3, 5, "JMP Indirect".to_string()),
-        0xD => opcode = set_opcode(0x6D, 3, 4, "ADC Absolute".to_string()),
-        0xE => opcode = set_opcode(0x6E, 3, 6, "ROR (ROtate Right) Absolute".to_string()),
-        0xF => opcode = set_opcode(0x6F, 0, 0, "Not used.".to_string()),
-        _   => println!("Not used."),
-    }
-    return opcode;
+    println!("{:?}", opcode);
 }
 
-fn left_nib_is_7(rn: u8) -> Opcode
+pub fn get_opcode_length(opcode: Opcode) -> usize
 {
-    let mut opcode = set_opcode(0x00, 0, 0, " ".to_string());
-    match rn
-    {
-        0x0 => opcode = set_opcode(0x70, 2, 2, "BVS (Branch on oVerflow Set)".to_string()),
-        // add 1 cycle if page boundary crossed
-        0x1 => opcode = set_opcode(0x71, 2, 5, "ADC Indirect, Y".to_string()),
-        0x2 => opcode = set_opcode(0x72, 0, 0, "Not used.".to_string()),
-        0x3 => opcode = set_opcode(0x73, 0, 0, "Not used.".to_string()),
-        0x4 => opcode = set_opcode(0x74, 0, 0, "Not used.".to_string()),
-        0x5 => opcode = set_opcode(0x75, 2, 4, "ADC Zero Page, X".to_string()),
-        0x6 => opcode = set_opcode(0x76, 2, 6, "ROR (ROtate Right) Zero Page, X".to_string()),
-        0x7 => opcode = set_opcode(0x77, 0, 0, "Not used.".to_string()),
-        0x8 => opcode = set_opcode(0x78, 1, 2, "SEI (SEt Interrupt)".to_string()),
-        // add 1 cycle if page boundary crossed
-        0x9 => opcode = set_opcode(0x79, 3, 4, "ADC Absolute, Y".to_string()),
-        0xA => opcode = set_opcode(0x7A, 0, 0, "Not used.".to_string()),
-        0xB => opcode = set_opcode(0x7B, 0, 0, "Not used.".to_string()),
-        0xC => opcode = set_opcode(0x7C, 0, 0, "Not used.".to_string()),
-        // add 1 cycle if page boundary crossed
-        0xD => opcode = set_opcode(0x7D, 3, 4, "ADC Absolute, X".to_string()),
-        0xE => opcode = set_opcode(0x7E, 3, 7, "ROR (ROtate Right) Absolute, X".to_string()),
-        0xF => opcode = set_opcode(0x7F, 0, 0, "Not used.".to_string()),
-        _   => println!("Not used."),
-    }
-    return opcode;
+    return opcode.length;
 }
 
-fn left_nib_is_8(rn: u8) -> Opcode
+pub fn get_opcode_cycles(opcode: Opcode) -> u8
 {
-    let mut opcode = set_opcode(0x00, 0, 0, " ".to_string());
-    match rn
-    {
-        0x0 => opcode = set_opcode(0x80, 0, 0, "Not used.".to_string()),
-        0x1 => opcode = set_opcode(0x81, 2, 6, "STA Indirect, X".to_string()),
-        0x2 => opcode = set_opcode(0x82, 0, 0, "Not used.".to_string()),
-        0x3 => opcode = set_opcode(0x83, 0, 0, "Not used.".to_string()),
-        0x4 => opcode = set_opcode(0x84, 2, 3, "STY Zero Page".to_string()),
-        0x5 => opcode = set_opcode(0x85, 2, 3, "STA Zero Page".to_string()),
-        0x6 => opcode = set_opcode(0x86, 2, 3, "STX Zero Page".to_string()),
-        0x7 => opcode = set_opcode(0x87, 0, 0, "Not used.".to_string()),
-        0x8 => opcode = set_opcode(0x88, 1, 2, "DEY (DEcrement Y)".to_string()),
-        0x9 => opcode = set_opcode(0x89, 0, 0, "Not used.".to_string()),
-        0xA => opcode = set_opcode(0x8A, 1, 2, "TXA (TRansfer X to A)".to_string()),
-        0xB => opcode = set_opcode(0x8B, 0, 0, "Not used.".to_string()),
-        0xC => opcode = set_opcode(0x8C, 3, 4, "STY Absolute".to_string()),
-        0xD => opcode = set_opcode(0x8D, 3, 4, "STA Absolute".to_string()),
-        0xE => opcode = set_opcode(0x8E, 3, 4, "STX Absolute".to_string()),
-        0xF => opcode = set_opcode(0x8F, 0, 0, "Not used.".to_string()),
-        _   => println!("Not used."),
-    }
-    return opcode;
+    return opcode.cycles;
 }
 
-fn left_nib_is_9(rn: u8) -> Opcode
+pub fn print_opcode_description(opcode: Opcode)
 {
-    let mut opcode = set_opcode(0x00, 0, 0, " ".to_string());
-    match rn
-    {
-        0x0 => opcode = set_opcode(0x90, 2, 2, "BCC (Branch on Carry Clear)".to_string()),
-        0x1 => opcode = set_opcode(0x91, 2, 6, "STA Indirect, Y".to_string()),
-        0x2 => opcode = set_opcode(0x92, 0, 0, "Not used.".to_string()),
-        0x3 => opcode = set_opcode(0x93, 0, 0, "Not used.".to_string()),
-        0x4 => opcode = set_opcode(0x94, 2, 4, "STY Zero Page, X".to_string()),
-        0x5 => opcode = set_opcode(0x95, 2, 4, "STA Zero Page, X".to_string()),
-        0x6 => opcode = set_opcode(0x96, 2, 4, "STX Zero Page, Y".to_string()),
-        0x7 => opcode = set_opcode(0x97, 0, 0, "Not used.".to_string()),
-        0x8 => opcode = set_opcode(0x98, 1, 2, "TYA (Transfer Y to A)".to_string()),
-        0x9 => opcode = set_opcode(0x99, 3, 5, "STA Absolute, Y".to_string()),
-        0xA => opcode = set_opcode(0x9A, 1, 2, "TXS (Transfer X to Stack ptr)".to_string()),
-        0xB => opcode = set_opcode(0x9B, 0, 0, "Not used.".to_string()),
-        0xC => opcode = set_opcode(0x9C, 0, 0, "Not used.".to_string()),
-        0xD => opcode = set_opcode(0x9D, 3, 5, "STA Absolute, X".to_string()),
-        0xE => opcode = set_opcode(0x9E, 0, 0, "Not used.".to_string()),
-        0xF => opcode = set_opcode(0x9F, 0, 0, "Not used.".to_string()),
-        _   => println!("Not used."),
-    }
-    return opcode;
+    println!("{}", opcode.description);
 }
 
-fn left_nib_is_a(rn: u8) -> Opcode
+pub fn get_opcode_description(opcode: Opcode) -> String
 {
-    let mut opcode = set_opcode(0x00, 0, 0, " ".to_string());
-    match rn
-    {
-        0x0 => opcode = set_opcode(0xA0, 2, 2, "LDY Immediate".to_string()),
-        0x1 => opcode = set_opcode(0xA1, 2, 6, "LDA Indirect, X".to_string()),
-        0x2 => opcode = set_opcode(0xA2, 2, 2, "LDX Immediate".to_string()),
-        0x3 => opcode = set_opcode(0xA3, 0, 0, "Not used.".to_string()),
-        0x4 => opcode = set_opcode(0xA4, 2, 3, "LDY Zero Page".to_string()),
-        0x5 => opcode = set_opcode(0xA5, 2, 3, "LDA Zero Page".to_string()),
-        0x6 => opcode = set_opcode(0xA6, 2, 3, "LDX Zero Page".to_string()),
-        0x7 => opcode = set_opcode(0xA7, 0, 0, "Not used.".to_string()),
-        0x8 => opcode = set_opcode(0xA8, 1, 2, "TAY (Transfer A to Y)".to_string()),
-        0x9 => opcode = set_opcode(0xA9, 2, 2, "LDA Immediate".to_string()),
-        0xA => opcode = set_opcode(0xAA, 1, 2, "TAX (Transfer A to X)".to_string()),
-        0xB => opcode = set_opcode(0xAB, 0, 0, "Not used.".to_string()),
-        0xC => opcode = set_opcode(0xAC, 3, 4, "LDY Absolute".to_string()),
-        0xD => opcode = set_opcode(0xAD, 3, 4, "LDA Absolute".to_string()),
-        0xE => opcode = set_opcode(0xAE, 3, 4, "LDX Absolute".to_string()),
-        0xF => opcode = set_opcode(0xAF, 0, 0, "Not used.".to_string()),
-        _   => println!("Not used."),
-    }
-    return opcode;
+    return opcode.description;
 }
 
-fn left_nib_is_b(rn: u8) -> Opcode
+pub fn get_opcode_code(opcode: Opcode) -> u8
 {
-    let mut opcode = set_opcode(0x00, 0, 0, " ".to_string());
-    match rn
-    {
-        0x0 => opcode = set_opcode(0xB0, 2, 2, "BCS (Branch on Carry Set)".to_string()),
-        // add 1 cycle if page boundary crossed
-        0x1 => opcode = set_opcode(0xB1, 2, 5, "LDA Indirect, Y".to_string()),
-        0x2 => opcode = set_opcode(0xB2, 0, 0, "Not used.".to_string()),
-        0x3 => opcode = set_opcode(0xB3, 0, 0, "Not used.".to_string()),
-        0x4 => opcode = set_opcode(0xB4, 2, 4, "LDY Zero Page, X".to_string()),
-        0x5 => opcode = set_opcode(0xB5, 2, 4, "LDA Zero Page, X".to_string()),
-        0x6 => opcode = set_opcode(0xB6, 2, 4, "LDX Zero Page, Y".to_string()),
-        0x7 => opcode = set_opcode(0xB7, 0, 0, "Not used.".to_string()),
-        0x8 => opcode = set_opcode(0xB8, 1, 2, "CLV (CLear oVerflow)".to_string()),
-        // add 1 cycle if page boundary crossed
-        0x9 => opcode = set_opcode(0xB9, 3, 4, "LDA Absolute, Y".to_string()),
-        0xA => opcode = set_opcode(0xBA, 1, 2, "TSX (Transfer Stack ptr to X)".to_string()),
-        0xB => opcode = set_opcode(0xBB, 0, 0, "Not used.".to_string()),
-        // add 1 cycle if page boundary crossed
-        0xC => opcode = set_opcode(0xBC, 3, 4, "LDY Absolute, X".to_string()),
-        // add 1 cycle if page boundary crossed
-        0xD => opcode = set_opcode(0xBD, 3, 4, "LDA Absolute, X".to_string()),
-        // add 1 cycle if page boundary crossed
-        0xE => opcode = set_opcode(0xBE, 3, 4, "LDX Absolute, Y".to_string()),
-        0xF => opcode = set_opcode(0xBF, 0, 0, "Not used.".to_string()),
-        _   => println!("Not used."),
-    }
-    return opcode;
+    return opcode.code;
 }
 
-fn left_nib_is_c(rn: u8) -> Opcode
+pub fn describe(entry: OpcodeEntry) -> String
 {
-    let mut opcode = set_opcode(0x00, 0, 0, " ".to_string());
-    match rn
+    let mut description = format!("{} ({})", entry.mnemonic.name(), entry.mnemonic.long_name());
+    if !entry.mode.description().is_empty()
+    {
+        description.push(' ');
+        description.push_str(entry.mode.description());
+    }
+    if !entry.official
     {
-        0x0 => opcode = set_opcode(0xC0, 2, 2, "CPY (ComPare Y register) Immediate".to_string()),
-        0x1 => opcode = set_opcode(0xC1, 2, 6, "CMP (CoMPare accumulator) Indirect, X".to_string()),
-        0x2 => opcode = set_opcode(0xC2, 0, 0, "Not used.".to_string()),
-        0x3 => opcode = set_opcode(0xC3, 0, 0, "Not used.".to_string()),
-        0x4 => opcode = set_opcode(0xC4, 2, 3, "CPY (ComPare Y register) Zero Page".to_string()),
-        0x5 => opcode = set_opcode(0xC5, 2, 3, "CMP (CoMPare accumulator) Zero Page".to_string()),
-        0x6 => opcode = set_opcode(0xC6, 2, 5, "DEC (DECrement memory) Zero Page".to_string()),
-        0x7 => opcode = set_opcode(0xC7, 0, 0, "Not used.".to_string()),
-        0x8 => opcode = set_opcode(0xC8, 1, 2, "INY (INcrement Y)".to_string()),
-        0x9 => opcode = set_opcode(0xC9, 2, 2, "CMP (CoMPare accumulator) Immediate".to_string()),
-        0xA => opcode = set_opcode(0xCA, 1, 2, "DEX (DEcrement X)".to_string()),
-        0xB => opcode = set_opcode(0xCB, 0, 0, "Not used.".to_string()),
-        0xC => opcode = set_opcode(0xCC, 3, 4, "CPY (ComPare Y register) Absolute".to_string()),
-        0xD => opcode = set_opcode(0xCD, 3, 4, "CMP (CoMPare accumulator) Absolute".to_string()),
-        0xE => opcode = set_opcode(0xCE, 3, 6, "DEC (DECrement memory) Absolute".to_string()),
-        0xF => opcode = set_opcode(0xCF, 0, 0, "Not used.".to_string()),
-        _   => println!("Not used."),
+        description.push_str(" [unofficial]");
     }
-    return opcode;
+    return description;
 }
 
-fn left_nib_is_d(rn: u8) -> Opcode
+// Branch offsets are signed and relative to the instruction after the branch.
+pub fn branch_target(next_pc: u16, offset: u8) -> u16
 {
-    let mut opcode = set_opcode(0x00, 0, 0, " ".to_string());
-    match rn
-    {
-        0x0 => opcode = set_opcode(0xD0, 2, 2, "BNE (Branch on Not Equal)".to_string()),
-        // add 1 cycle if page boundary crossed
-        0x1 => opcode = set_opcode(0xD1, 2, 5, "CMP (CoMPare accumulator) Indirect, Y".to_string()),
-        0x2 => opcode = set_opcode(0xD2, 0, 0, "Not used.".to_string()),
-        0x3 => opcode = set_opcode(0xD3, 0, 0, "Not used.".to_string()),
-        0x4 => opcode = set_opcode(0xD4, 0, 0, "Not used.".to_string()),
-        0x5 => opcode = set_opcode(0xD5, 2, 4, "CMP (CoMPare accumulator) Zero Page, X".to_string()),
-        0x6 => opcode = set_opcode(0xD6, 2, 6, "DEC (DECrement memory) Zero Page, X".to_string()),
-        0x7 => opcode = set_opcode(0xD7, 0, 0, "Not used.".to_string()),
-        0x8 => opcode = set_opcode(0xD8, 1, 2, "CLD (CLear Decimal)".to_string()),
-        // add 1 cycle if page boundary crossed
-        0x9 => opcode = set_opcode(0xD9, 3, 4, "CMP (CoMPare accumulator) Absolute, Y".to_string()),
-        0xA => opcode = set_opcode(0xDA, 0, 0, "Not used.".to_string()),
-        0xB => opcode = set_opcode(0xDB, 0, 0, "Not used.".to_string()),
-        0xC => opcode = set_opcode(0xDC, 0, 0, "Not used.".to_string()),
-        // add 1 cycle if page boundary crossed
-        0xD => opcode = set_opcode(0xDD, 3, 4, "CMP (CoMPare accumulator) Absolute, X".to_string()),
-        0xE => opcode = set_opcode(0xDE, 3, 7, "DEC (DECrement memory) Absolute, X".to_string()),
-        0xF => opcode = set_opcode(0xDF, 0, 0, "Not used.".to_string()),
-        _   => println!("Not used."),
-    }
-    return opcode;
+    return next_pc.wrapping_add(offset as i8 as u16);
 }
 
-fn left_nib_is_e(rn: u8) -> Opcode
+// Assembler style text for one instruction, e.g. "LDA ($44),Y" or "BNE $C72D".
+// Branches show their resolved target instead of the raw offset.
+pub fn disassemble(pc: u16, code: u8, first_byte: u8, second_byte: u8) -> String
 {
-    let mut opcode = set_opcode(0x00, 0, 0, " ".to_string());
-    match rn
+    let entry = decode(code);
+    let word = ((second_byte as u16) << 8) | first_byte as u16;
+    let operand = match entry.mode
     {
-        0x0 => opcode = set_opcode(0xE0, 2, 2, "CPX (ComPare X register) Immediate".to_string()),
-        0x1 => opcode = set_opcode(0xE1, 2, 6, "SBC (SuBtract with Carry) Indirect, X".to_string()),
-        0x2 => opcode = set_opcode(0xE2, 0, 0, "Not used.".to_string()),
-        0x3 => opcode = set_opcode(0xE3, 0, 0, "Not used.".to_string()),
-        0x4 => opcode = set_opcode(0xE4, 2, 3, "CPX (ComPare X register) Zero Page".to_string()),
-        0x5 => opcode = set_opcode(0xE5, 2, 3, "SBC (SuBtract with Carry) Zero Page".to_string()),
-        0x6 => opcode = set_opcode(0xE6, 2, 5, "INC (INCrement memory) Zero Page".to_string()),
-        0x7 => opcode = set_opcode(0xE7, 0, 0, "Not used.".to_string()),
-        0x8 => opcode = set_opcode(0xE8, 1, 2, "INX (INcrement X)".to_string()),
-        0x9 => opcode = set_opcode(0xE9, 2, 2, "SBC (SuBtract with Carry) Immediate".to_string()),
-        0xA => opcode = set_opcode(0xEA, 1, 2, "NOP".to_string()),
-        0xB => opcode = set_opcode(0xEB, 0, 0, "Not used.".to_string()),
-        0xC => opcode = set_opcode(0xEC, 3, 4, "CPX (ComPare X register) Absolute".to_string()),
-        0xD => opcode = set_opcode(0xED, 3, 4, "SBC (SuBtract with Carry) Absolute".to_string()),
-        0xE => opcode = set_opcode(0xEE, 3, 6, "INC (INCrement memory) Absolute".to_string()),
-        0xF => opcode = set_opcode(0xEF, 0, 0, "Not used.".to_string()),
-        _   => println!("Not used."),
+        Implied     => String::new(),
+        Accumulator => "A".to_string(),
+        Immediate   => format!("#${:02X}", first_byte),
+        ZeroPage    => format!("${:02X}", first_byte),
+        ZeroPageX   => format!("${:02X},X", first_byte),
+        ZeroPageY   => format!("${:02X},Y", first_byte),
+        Relative    => format!("${:04X}", branch_target(pc.wrapping_add(entry.length as u16), first_byte)),
+        Absolute    => format!("${:04X}", word),
+        AbsoluteX   => format!("${:04X},X", word),
+        AbsoluteY   => format!("${:04X},Y", word),
+        Indirect    => format!("(${:04X})", word),
+        IndirectX   => format!("(${:02X},X)", first_byte),
+        IndirectY   => format!("(${:02X}),Y", first_byte),
+    };
+
+    if operand.is_empty()
+    {
+        return entry.mnemonic.name().to_string();
     }
-    return opcode;
+    return format!("{} {}", entry.mnemonic.name(), operand);
 }
 
-fn left_nib_is_f(rn: u8) -> Opcode
+pub fn build_opcode(byte: u8) -> Opcode
 {
-    let mut opcode = set_opcode(0x00, 0, 0, " ".to_string());
-    match rn
-    {
-        0x0 => opcode = set_opcode(0xF0, 2, 2, "BEQ (Branch on EQual)".to_string()),
-        // add 1 cycle if page boundary crossed
-        0x1 => opcode = set_opcode(0xF1, 2, 5, "SBC (SuBtract with Carry) Indirect, Y".to_string()),
-        0x2 => opcode = set_opcode(0xF2, 0, 0, "Not used.".to_string()),
-        0x3 => opcode = set_opcode(0xF3, 0, 0, "Not used.".to_string()),
-        0x4 => opcode = set_opcode(0xF4, 0, 0, "Not used.".to_string()),
-        0x5 => opcode = set_opcode(0xF5, 2, 4, "SBC (SuBtract with Carry) Zero Page, X".to_string()),
-        0x6 => opcode = set_opcode(0xF6, 2, 6, "INC (INCrement memory) Zero Page, X".to_string()),
-        0x7 => opcode = set_opcode(0xF7, 0, 0, "Not used.".to_string()),
-        0x8 => opcode = set_opcode(0xF8, 1, 2, "SED (SEt Decimal)".to_string()),
-        // add 1 cycle if page boundary crossed
-        0x9 => opcode = set_opcode(0xF9, 3, 4, "SBC (SuBtract with Carry) Absolute, Y".to_string()),
-        0xA => opcode = set_opcode(0xFA, 0, 0, "Not used.".to_string()),
-        0xB => opcode = set_opcode(0xFB, 0, 0, "Not used.".to_string()),
-        0xC => opcode = set_opcode(0xFC, 0, 0, "Not used.".to_string()),
-        // add 1 cycle if page boundary crossed
-        0xD => opcode = set_opcode(0xFD, 3, 4, "SBC (SuBtract with Carry) Absolute, X".to_string()),
-        0xE => opcode = set_opcode(0xFE, 3, 7, "INC (INCrement memory) Absolute, X".to_string()),
-        0xF => opcode = set_opcode(0xFF, 0, 0, "Not used.".to_string()),
-        _   => println!("Not used."),
-    }
-    return opcode;
+    let entry = decode(byte);
+    return set_opcode(byte, entry.length, entry.cycles, describe(entry));
 }
@@ -0,0 +1,140 @@
+use crate::file_handling::*;
+use crate::header::*;
+
+// a header followed by the given amount of zeroed trainer/ROM data
+fn image(prg_banks: u8, chr_banks: u8, flags_6: u8, flags_7: u8, data: usize) -> Vec<u8>
+{
+    let mut buffer = vec![0x4E, 0x45, 0x53, 0x1A, prg_banks, chr_banks, flags_6, flags_7];
+    buffer.resize(0x10 + data, 0x0);
+    return buffer;
+}
+
+#[test]
+fn splits_prg_and_chr()
+{
+    let mut buffer = image(2, 1, 0x00, 0x00, 0x8000 + 0x2000);
+    buffer[0x10] = 0x78; // first PRG byte
+    buffer[0x10 + 0x8000] = 0xAA; // first CHR byte
+
+    let rom = parse_rom(&buffer).unwrap();
+    assert_eq!(rom.get_prg_rom().len(), 0x8000);
+    assert_eq!(rom.get_chr_rom().len(), 0x2000);
+    assert_eq!(rom.get_prg_rom()[0], 0x78);
+    assert_eq!(rom.get_chr_rom()[0], 0xAA);
+    assert!(rom.get_trainer().is_none());
+}
+
+#[test]
+fn trainer_comes_before_prg()
+{
+    let mut buffer = image(1, 0, 0x04, 0x00, 0x200 + 0x4000);
+    buffer[0x10] = 0x11;
+    buffer[0x210] = 0x22;
+
+    let rom = parse_rom(&buffer).unwrap();
+    assert_eq!(rom.get_trainer().unwrap()[0], 0x11);
+    assert_eq!(rom.get_prg_rom()[0], 0x22);
+}
+
+#[test]
+fn accepts_announced_trailers()
+{
+    // PlayChoice INST-ROM and PROM
+    let buffer = image(1, 1, 0x00, 0x02, 0x4000 + 0x2000 + 0x2020);
+    let rom = parse_rom(&buffer).unwrap();
+    assert_eq!(rom.get_chr_rom().len(), 0x2000);
+
+    // a trailer of the wrong size is still a mismatch
+    let buffer = image(1, 1, 0x00, 0x02, 0x4000 + 0x2000 + 0x2000);
+    assert!(matches!(parse_rom(&buffer), Err(RomError::SizeMismatch { expected: 0x6010, actual: 0x8010 })));
+
+    // NES 2.0 with one misc ROM
+    let mut buffer = image(1, 0, 0x00, 0x08, 0x4000 + 0x1234);
+    buffer[0xE] = 0x01;
+    let rom = parse_rom(&buffer).unwrap();
+    assert_eq!(rom.get_prg_rom().len(), 0x4000);
+
+    // without misc ROMs the same file is oversized
+    buffer[0xE] = 0x00;
+    assert!(matches!(parse_rom(&buffer), Err(RomError::SizeMismatch { expected: 0x4010, actual: 0x5244 })));
+}
+
+#[test]
+fn reports_bad_files()
+{
+    assert!(matches!(parse_rom(&[0x4E, 0x45, 0x53]), Err(RomError::TooShort(3))));
+
+    let mut buffer = image(1, 0, 0x00, 0x00, 0x4000);
+    buffer[3] = 0x00;
+    assert!(matches!(parse_rom(&buffer), Err(RomError::BadMagic(_))));
+
+    let buffer = image(2, 1, 0x00, 0x00, 0x4000);
+    assert!(matches!(parse_rom(&buffer), Err(RomError::SizeMismatch { expected: 0xA010, actual: 0x4010 })));
+
+    let buffer = image(1, 0, 0x00, 0x00, 0x4000 + 0x80);
+    assert!(matches!(parse_rom(&buffer), Err(RomError::SizeMismatch { expected: 0x4010, actual: 0x4090 })));
+
+    let buffer = image(1, 0, 0x04, 0x00, 0x100);
+    assert!(matches!(parse_rom(&buffer), Err(RomError::TrainerTruncated)));
+
+    let buffer = image(1, 0, 0x40, 0x50, 0x4000);
+    assert!(matches!(parse_rom(&buffer), Err(RomError::UnsupportedMapper(0x54))));
+
+    assert!(matches!(load_rom(String::from("does/not/exist.nes")), Err(RomError::Io(_))));
+}
+
+#[test]
+fn decodes_header_flags()
+{
+    // mapper 0x41, vertical mirroring, battery, VS System, 32KB PRG-RAM, PAL
+    let bytes = [0x4E, 0x45, 0x53, 0x1A, 0x08, 0x00, 0x13, 0x41, 0x04, 0x01, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0];
+    let h = parse_header(&bytes).unwrap();
+    assert_eq!(h.get_mapper(), 0x41);
+    assert_eq!(h.get_mirroring(), Mirroring::Vertical);
+    assert!(h.has_battery());
+    assert!(!h.has_trainer());
+    assert!(h.is_vs_unisystem());
+    assert!(!h.is_playchoice());
+    assert_eq!(h.get_prg_ram_size(), 0x8000);
+    assert_eq!(h.get_timing(), Timing::Pal);
+    assert_eq!(h.get_chr_rom_size(), 0);
+
+    let mut bytes = bytes;
+    bytes[0x6] = 0x09;
+    assert_eq!(parse_header(&bytes).unwrap().get_mirroring(), Mirroring::FourScreen);
+}
+
+#[test]
+fn ignores_garbage_in_archaic_headers()
+{
+    let mut bytes = *b"NES\x1A\x02\x01\x10\x00DiskDude!";
+    bytes[0x7] = b'D';
+    let h = parse_header(&bytes[..0x10]).unwrap();
+    assert_eq!(h.get_format(), HeaderFormat::Archaic);
+    assert_eq!(h.get_mapper(), 0x01);
+    assert!(!h.is_vs_unisystem());
+    assert_eq!(h.get_prg_ram_size(), 0x2000);
+}
+
+#[test]
+fn decodes_nes2_headers()
+{
+    // mapper 0x101 submapper 2, 0x804 PRG banks via the MSB nibble,
+    // CHR-ROM 2^13 * 3 via the exponent form, 8KB PRG-RAM, 32KB PRG-NVRAM,
+    // CHR-RAM 8KB, Dendy, extended console 3, 1 misc ROM, Zapper
+    let bytes = [0x4E, 0x45, 0x53, 0x1A, 0x04, 0x35, 0x12, 0x0B, 0x21, 0xF8, 0x97, 0x07, 0x03, 0x03, 0x01, 0x08];
+    let h = parse_header(&bytes).unwrap();
+    assert_eq!(h.get_format(), HeaderFormat::Nes2);
+    assert_eq!(h.get_mapper(), 0x101);
+    assert_eq!(h.get_submapper(), 2);
+    assert_eq!(h.get_prg_rom_size(), 0x804 * 0x4000);
+    assert_eq!(h.get_chr_rom_size(), (1 << 13) * 3);
+    assert_eq!(h.get_prg_ram_size(), 0x2000);
+    assert_eq!(h.get_prg_nvram_size(), 0x8000);
+    assert_eq!(h.get_chr_ram_size(), 0x2000);
+    assert_eq!(h.get_chr_nvram_size(), 0);
+    assert_eq!(h.get_timing(), Timing::Dendy);
+    assert_eq!(h.get_console_type(), ConsoleType::Extended(3));
+    assert_eq!(h.get_misc_rom_count(), 1);
+    assert_eq!(h.get_expansion_device(), 0x08);
+}
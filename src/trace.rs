@@ -0,0 +1,157 @@
+use crate::bus::Bus;
+use crate::cpu::CPU;
+use crate::opcode::*;
+
+// First line where our trace and a reference log disagree.
+#[derive(Debug, Clone)]
+pub struct Divergence
+{
+    pub line: usize, // 1 based, like a text editor
+    pub expected: String,
+    pub actual: String,
+}
+
+// nestest.log spells ISC the way Nintendulator does.
+fn trace_name(mnemonic: Mnemonic) -> &'static str
+{
+    match mnemonic
+    {
+        Mnemonic::Isc => "ISB",
+        _ => mnemonic.name(),
+    }
+}
+
+// Operand text with the memory it touches, e.g. "($80,X) @ 80 = 0200 = 5A".
+// Memory is peeked, so tracing never disturbs the bus.
+fn annotate_operand<B: Bus>(bus: &mut B, entry: OpcodeEntry, pc: u16, bytes: &[u8], x: u8, y: u8) -> String
+{
+    let first = if bytes.len() > 1 { bytes[1] } else { 0 };
+    let second = if bytes.len() > 2 { bytes[2] } else { 0 };
+    let word = ((second as u16) << 8) | first as u16;
+
+    match entry.mode
+    {
+        AddressingMode::Implied     => String::new(),
+        AddressingMode::Accumulator => "A".to_string(),
+        AddressingMode::Immediate   => format!("#${:02X}", first),
+        AddressingMode::ZeroPage    => format!("${:02X} = {:02X}", first, bus.peek(first as u16)),
+        AddressingMode::ZeroPageX   =>
+        {
+            let address = first.wrapping_add(x);
+            format!("${:02X},X @ {:02X} = {:02X}", first, address, bus.peek(address as u16))
+        },
+        AddressingMode::ZeroPageY   =>
+        {
+            let address = first.wrapping_add(y);
+            format!("${:02X},Y @ {:02X} = {:02X}", first, address, bus.peek(address as u16))
+        },
+        AddressingMode::Relative    => format!("${:04X}", branch_target(pc.wrapping_add(2), first)),
+        AddressingMode::Absolute    =>
+        {
+            if entry.mnemonic == Mnemonic::Jmp || entry.mnemonic == Mnemonic::Jsr
+            {
+                return format!("${:04X}", word);
+            }
+            format!("${:04X} = {:02X}", word, bus.peek(word))
+        },
+        AddressingMode::AbsoluteX   =>
+        {
+            let address = word.wrapping_add(x as u16);
+            format!("${:04X},X @ {:04X} = {:02X}", word, address, bus.peek(address))
+        },
+        AddressingMode::AbsoluteY   =>
+        {
+            let address = word.wrapping_add(y as u16);
+            format!("${:04X},Y @ {:04X} = {:02X}", word, address, bus.peek(address))
+        },
+        AddressingMode::Indirect    =>
+        {
+            // same page wrap as the cpu, JMP ($xxFF) takes its high byte from $xx00
+            let high_pointer = (word & 0xFF00) | (word.wrapping_add(1) & 0x00FF);
+            let target = ((bus.peek(high_pointer) as u16) << 8) | bus.peek(word) as u16;
+            format!("(${:04X}) = {:04X}", word, target)
+        },
+        AddressingMode::IndirectX   =>
+        {
+            let pointer = first.wrapping_add(x);
+            let address = peek_zero_page_word(bus, pointer);
+            format!("(${:02X},X) @ {:02X} = {:04X} = {:02X}", first, pointer, address, bus.peek(address))
+        },
+        AddressingMode::IndirectY   =>
+        {
+            let base = peek_zero_page_word(bus, first);
+            let address = base.wrapping_add(y as u16);
+            format!("(${:02X}),Y = {:04X} @ {:04X} = {:02X}", first, base, address, bus.peek(address))
+        },
+    }
+}
+
+fn peek_zero_page_word<B: Bus>(bus: &mut B, pointer: u8) -> u16
+{
+    let low = bus.peek(pointer as u16) as u16;
+    let high = bus.peek(pointer.wrapping_add(1) as u16) as u16;
+    return (high << 8) | low;
+}
+
+// One nestest.log style line describing the instruction about to run:
+// C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+pub fn trace_line<B: Bus>(cpu: &mut CPU<B>) -> String
+{
+    let pc = cpu.get_pc();
+    let a = cpu.get_a();
+    let x = cpu.get_x();
+    let y = cpu.get_y();
+    let p = cpu.get_p();
+    let s = cpu.get_s();
+    let cycles = cpu.get_cycles();
+
+    let bus = cpu.get_bus_mut();
+    let entry = decode(bus.peek(pc));
+    let mut bytes = Vec::new();
+    for i in 0..entry.length
+    {
+        bytes.push(bus.peek(pc.wrapping_add(i as u16)));
+    }
+    let raw: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+
+    // unofficial opcodes are flagged with a * in front of the mnemonic
+    let marker = if entry.official { ' ' } else { '*' };
+    let operand = annotate_operand(bus, entry, pc, &bytes, x, y);
+    let text = format!("{:04X}  {:<8} {}{} {}", pc, raw.join(" "), marker, trace_name(entry.mnemonic), operand);
+
+    // there is no PPU yet, so derive its position from the cpu clock,
+    // three dots per cycle, 341 dots per scanline, 262 scanlines per frame
+    let dots = cycles * 3;
+    let scanline = (dots / 341) % 262;
+    let dot = dots % 341;
+
+    return format!("{:<47} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:>3},{:>3} CYC:{}",
+                   text.trim_end(), a, x, y, p, s, scanline, dot, cycles);
+}
+
+// Steps the cpu once per reference line and stops at the first line that
+// does not match. Returns None when the whole log matched.
+pub fn compare_with_log<B: Bus>(cpu: &mut CPU<B>, reference: &str) -> Option<Divergence>
+{
+    for (i, expected) in reference.lines().enumerate()
+    {
+        let expected = expected.trim_end();
+        if expected.is_empty()
+        {
+            continue;
+        }
+
+        let actual = trace_line(cpu);
+        if actual != expected
+        {
+            return Some(Divergence { line: i + 1, expected: expected.to_string(), actual });
+        }
+        if cpu.is_halted()
+        {
+            return Some(Divergence { line: i + 2, expected: String::from("<more instructions>"),
+                                     actual: String::from("<cpu jammed>") });
+        }
+        cpu.step();
+    }
+    return None;
+}
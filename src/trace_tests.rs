@@ -0,0 +1,22 @@
+use crate::bus::*;
+use crate::cpu::*;
+use crate::trace::*;
+
+#[test]
+fn apu_registers_trace_as_ff()
+{
+    let mut cpu = init_cpu(init_nes_bus());
+    let program = [0x8D, 0x15, 0x40, 0xAD, 0x16, 0x40];
+    for (i, byte) in program.iter().enumerate()
+    {
+        cpu.get_bus_mut().write(0x0600 + i as u16, *byte);
+    }
+    cpu.set_pc(0x0600);
+    cpu.set_a(0x0F);
+
+    assert!(trace_line(&mut cpu).starts_with("0600  8D 15 40  STA $4015 = FF "));
+    cpu.step();
+    assert!(trace_line(&mut cpu).starts_with("0603  AD 16 40  LDA $4016 = FF "));
+    // the register itself still holds what was stored
+    assert_eq!(cpu.get_bus().get_apu_io_register(0x4015), 0x0F);
+}